@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use purse::Purse;
+use std::collections::hash_map::RandomState;
 
 pub fn purse_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("Purse");
@@ -19,6 +20,28 @@ pub fn purse_benchmark(c: &mut Criterion) {
         });
     });
 
+    // The same lookup with the `TypeId`s hashed by SipHash, for comparison with the
+    // default identity hasher above.
+    group.bench_function("contains_random_state", |b| {
+        let mut purse = Purse::with_hasher(RandomState::new());
+        purse.insert(42);
+        b.iter(|| {
+            purse.contains(black_box(42));
+        });
+    });
+
+    group.bench_function("get_all_of_type", |b| {
+        let mut purse = Purse::new();
+        (0..1_000).for_each(|n| purse.insert(n));
+        b.iter(|| black_box(purse.get_all_of_type::<i32>()).len());
+    });
+
+    group.bench_function("iter_of_type", |b| {
+        let mut purse = Purse::new();
+        (0..1_000).for_each(|n| purse.insert(n));
+        b.iter(|| black_box(purse.iter_of_type::<i32>()).sum::<i32>());
+    });
+
     group.finish();
 }
 
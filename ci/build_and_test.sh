@@ -14,6 +14,6 @@ if [ "$FEATURES" == "default" ]; then
     cargo build --locked
     cargo test
 else
-    cargo build --locked --features=atomic
-    cargo test --features=atomic
-fi
\ No newline at end of file
+    cargo build --locked --no-default-features
+    cargo test --no-default-features
+fi
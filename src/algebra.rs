@@ -0,0 +1,474 @@
+//! Multiset operations between two [`Purse`]s.
+//!
+//! Every operation works type by type: the multiplicity of each value in the
+//! result is computed from its multiplicities in both operands, for example
+//! the larger of the two for a union. Comparing values requires the
+//! [`VTable`](crate::vtable::VTable) recorded by
+//! [`Purse::insert_value`], so items of other types only take part where the
+//! result can be worked out from counts alone.
+//!
+//! Operations that would take the left-hand purse past a limit of its
+//! [`Quota`] panic before making any change.
+
+use core::any::TypeId;
+use core::hash::BuildHasher;
+use core::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Sub, SubAssign,
+};
+
+use crate::column::ListColumn;
+use crate::map::{HashMap, HashSet};
+use crate::vtable::{multiplicities, runs, ErasedValue};
+use crate::{Bucket, Limit, Purse, PurseError, Quota};
+
+/// A multiset operation, applied per value to the multiplicities `a` and `b`
+/// of the left and right operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SetOp {
+    Union,
+    Sum,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+impl SetOp {
+    fn apply(self, a: u64, b: u64) -> u64 {
+        match self {
+            SetOp::Union => a.max(b),
+            SetOp::Sum => a.saturating_add(b),
+            SetOp::Intersection => a.min(b),
+            SetOp::Difference => a.saturating_sub(b),
+            SetOp::SymmetricDifference => a.abs_diff(b),
+        }
+    }
+}
+
+/// Updates `ours` in place so each value ends up with `op` applied to its
+/// multiplicities in `ours` and `theirs`.
+///
+/// Surplus copies are removed starting from the first occurrence, and missing
+/// copies are cloned from `theirs` and appended in the order they occur there,
+/// so untouched items keep their relative order.
+pub(crate) fn combine<T: ErasedValue>(ours: &mut Bucket, theirs: &Bucket, op: SetOp) {
+    let their_counts = multiplicities::<T>(theirs);
+    let mut surplus: HashMap<T, u64> = HashMap::default();
+    let mut missing: HashMap<&T, u64> = HashMap::default();
+    {
+        let our_counts = multiplicities::<T>(ours);
+        for (&value, &a) in &our_counts {
+            let b = their_counts.get(value).copied().unwrap_or(0);
+            let target = op.apply(a, b);
+            if target < a {
+                surplus.insert(value.clone(), a - target);
+            }
+        }
+        for (&value, &b) in &their_counts {
+            let a = our_counts.get(value).copied().unwrap_or(0);
+            let target = op.apply(a, b);
+            if target > a {
+                missing.insert(value, target - a);
+            }
+        }
+    }
+
+    let Some(column) = ours.column_mut::<T>() else {
+        return;
+    };
+    if !surplus.is_empty() {
+        match column
+            .as_any_mut()
+            .and_then(|c| c.downcast_mut::<ListColumn<T>>())
+        {
+            // A list is filtered in one pass rather than searched once per value.
+            Some(ListColumn(elems)) => elems.retain(|elem| match surplus.get_mut(elem) {
+                Some(n) if *n > 0 => {
+                    *n -= 1;
+                    false
+                }
+                _ => true,
+            }),
+            None => {
+                for (value, n) in &surplus {
+                    column.remove_n(value, *n);
+                }
+            }
+        }
+    }
+    if !missing.is_empty() {
+        for (value, run) in runs(theirs.iter_of::<T>()) {
+            if let Some(n) = missing.get_mut(value).filter(|n| **n > 0) {
+                let take = run.min(*n);
+                *n -= take;
+                column.insert_n(value.clone(), take);
+            }
+        }
+    }
+}
+
+/// Returns how many items [`combine`] would leave in `ours`.
+pub(crate) fn combined_len<T: ErasedValue>(ours: &Bucket, theirs: &Bucket, op: SetOp) -> u64 {
+    let our_counts = multiplicities::<T>(ours);
+    let their_counts = multiplicities::<T>(theirs);
+    let only_ours = our_counts
+        .iter()
+        .filter(|(value, _)| !their_counts.contains_key(*value))
+        .map(|(_, &a)| op.apply(a, 0));
+    let shared = their_counts.iter().map(|(value, &b)| {
+        let a = our_counts.get(value).copied().unwrap_or(0);
+        op.apply(a, b)
+    });
+    only_ours.chain(shared).sum()
+}
+
+/// Checks that no value occurs more often in `ours` than in `theirs`.
+pub(crate) fn is_subset<T: ErasedValue>(ours: &Bucket, theirs: &Bucket) -> bool {
+    if ours.len() > theirs.len() {
+        return false;
+    }
+    let their_counts = multiplicities::<T>(theirs);
+    multiplicities::<T>(ours)
+        .iter()
+        .all(|(value, &a)| their_counts.get(value).is_some_and(|&b| a <= b))
+}
+
+impl<S: BuildHasher + Clone> Purse<S> {
+    /// Applies `op` to every type held by either purse, storing the result in `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics before making any change if a type's result depends on comparing or cloning
+    /// elements of a type that neither purse has a vtable for, or if the result would
+    /// exceed the quota of `self`.
+    fn combine_with(&mut self, other: &Purse<S>, op: SetOp) {
+        let types: HashSet<TypeId> = self
+            .non_empty_buckets()
+            .chain(other.non_empty_buckets())
+            .map(|(type_id, _)| *type_id)
+            .collect();
+
+        for type_id in &types {
+            if self.vtables.contains_key(type_id) || other.vtables.contains_key(type_id) {
+                continue;
+            }
+            let a = self.len_of(type_id);
+            let b = other.len_of(type_id);
+            let needs_values = match op {
+                SetOp::Union | SetOp::Sum | SetOp::SymmetricDifference => b > 0,
+                SetOp::Intersection | SetOp::Difference => a > 0 && b > 0,
+            };
+            if needs_values {
+                let bucket = other.data.get(type_id).or(self.data.get(type_id));
+                let type_name = bucket.map_or("<unknown>", Bucket::type_name);
+                panic!(
+                    "cannot combine purses holding `{type_name}`, \
+                     which was not inserted with `insert_value`"
+                );
+            }
+        }
+        self.assert_combined_quota(other, op, &types);
+
+        for type_id in types {
+            let vtable = self
+                .vtables
+                .get(&type_id)
+                .or(other.vtables.get(&type_id))
+                .copied();
+            let Some(vtable) = vtable else {
+                // Only reachable when the result can be told from counts alone: all items
+                // are kept, except for an intersection with a purse that has none.
+                if op == SetOp::Intersection {
+                    self.data.remove(&type_id);
+                }
+                continue;
+            };
+            self.vtables.insert(type_id, vtable);
+            let empty = (vtable.empty)();
+            let theirs = other.data.get(&type_id).unwrap_or(&empty);
+            let factory = self.factory_or_default(&type_id, &vtable);
+            let ours = self.data.entry(type_id).or_insert_with(factory);
+            (vtable.combine)(ours, theirs, op);
+        }
+    }
+    /// Panics if applying `op` would take `self` past a limit of its [`Quota`] that it
+    /// isn't past already, the way [`insert`](Purse::insert) does.
+    fn assert_combined_quota(&self, other: &Purse<S>, op: SetOp, types: &HashSet<TypeId>) {
+        if self.quota == Quota::default() {
+            return;
+        }
+        let exceeded = |type_name, limit| -> ! {
+            panic!("{}", PurseError::QuotaExceeded { type_name, limit })
+        };
+        let (mut len, mut bytes) = (0usize, 0usize);
+        let mut grown = None;
+        for type_id in types {
+            let (ours, theirs) = (self.data.get(type_id), other.data.get(type_id));
+            let Some(bucket) = ours.or(theirs) else {
+                continue;
+            };
+            let before = self.len_of(type_id);
+            let after = match self.vtables.get(type_id).or(other.vtables.get(type_id)) {
+                Some(vtable) => {
+                    let empty = (vtable.empty)();
+                    let (ours, theirs) = (ours.unwrap_or(&empty), theirs.unwrap_or(&empty));
+                    usize::try_from((vtable.combined_len)(ours, theirs, op)).unwrap_or(usize::MAX)
+                }
+                None if op == SetOp::Intersection && other.len_of(type_id) == 0 => 0,
+                None => before,
+            };
+            len = len.saturating_add(after);
+            bytes = bytes.saturating_add(after.saturating_mul(bucket.0.value_size()));
+            if after > before {
+                if let Some(max) = self.quota.max_per_type.filter(|&max| after > max) {
+                    exceeded(bucket.type_name(), Limit::PerType(max));
+                }
+                grown.get_or_insert(bucket.type_name());
+            }
+        }
+        let Some(type_name) = grown else {
+            return;
+        };
+        if let Some(max) = self
+            .quota
+            .max_len
+            .filter(|&max| len > max && len > self.len())
+        {
+            exceeded(type_name, Limit::Len(max));
+        }
+        let used: usize = self.data.values().map(Bucket::len_bytes).sum();
+        if let Some(max) = self
+            .quota
+            .max_bytes
+            .filter(|&max| bytes > max && bytes > used)
+        {
+            exceeded(type_name, Limit::Bytes(max));
+        }
+    }
+    fn combined(&self, other: &Purse<S>, op: SetOp) -> Purse<S> {
+        let mut result = self.clone();
+        result.combine_with(other, op);
+        result
+    }
+    /// Returns the union of two purses.
+    ///
+    /// Each element occurs as many times as in whichever purse holds more copies of it.
+    /// The in-place form is `purse |= &other`.
+    ///
+    /// Like all multiset operations, this compares elements type by type using the
+    /// implementations recorded by [`insert_value`](Purse::insert_value).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` can't be [cloned](Purse::try_clone), if elements of a type without
+    /// a recorded vtable would need to be compared or cloned, or if the result would exceed
+    /// the [`Quota`] of `self`, which it inherits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value(1);
+    /// a.insert_value(1);
+    /// let mut b = Purse::new();
+    /// b.insert_value(1);
+    /// b.insert_value("x");
+    /// let union = a.union(&b);
+    /// assert_eq!(union.count_of(&1), 2);
+    /// assert_eq!(union.count_of(&"x"), 1);
+    /// ```
+    pub fn union(&self, other: &Purse<S>) -> Purse<S> {
+        self.combined(other, SetOp::Union)
+    }
+    /// Returns the sum of two purses, holding every element of both.
+    ///
+    /// Each element occurs as many times as in both purses together. The in-place form is
+    /// `purse += &other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`union`](Purse::union).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value(1);
+    /// let mut b = Purse::new();
+    /// b.insert_value(1);
+    /// assert_eq!(a.sum(&b).count_of(&1), 2);
+    /// ```
+    pub fn sum(&self, other: &Purse<S>) -> Purse<S> {
+        self.combined(other, SetOp::Sum)
+    }
+    /// Returns the intersection of two purses.
+    ///
+    /// Each element occurs as many times as in whichever purse holds fewer copies of it.
+    /// The in-place form is `purse &= &other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`union`](Purse::union).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value(1);
+    /// a.insert_value(1);
+    /// a.insert_value(2);
+    /// let mut b = Purse::new();
+    /// b.insert_value(1);
+    /// let common = a.intersection(&b);
+    /// assert_eq!(common.count_of(&1), 1);
+    /// assert!(!common.contains(2));
+    /// ```
+    pub fn intersection(&self, other: &Purse<S>) -> Purse<S> {
+        self.combined(other, SetOp::Intersection)
+    }
+    /// Returns the elements of `self` that are not matched by an element of `other`.
+    ///
+    /// Each element of `other` cancels out one copy of an equal element in `self`. The
+    /// in-place form is `purse -= &other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`union`](Purse::union).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value(1);
+    /// a.insert_value(1);
+    /// let mut b = Purse::new();
+    /// b.insert_value(1);
+    /// b.insert_value(2);
+    /// let rest = a.difference(&b);
+    /// assert_eq!(rest.count_of(&1), 1);
+    /// assert!(!rest.contains(2));
+    /// ```
+    pub fn difference(&self, other: &Purse<S>) -> Purse<S> {
+        self.combined(other, SetOp::Difference)
+    }
+    /// Returns the elements that one purse holds more copies of than the other.
+    ///
+    /// Each element occurs as many times as the difference between its counts in both
+    /// purses. The in-place form is `purse ^= &other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`union`](Purse::union).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value(1);
+    /// a.insert_value(1);
+    /// a.insert_value(1);
+    /// let mut b = Purse::new();
+    /// b.insert_value(1);
+    /// b.insert_value(2);
+    /// let diff = a.symmetric_difference(&b);
+    /// assert_eq!(diff.count_of(&1), 2);
+    /// assert_eq!(diff.count_of(&2), 1);
+    /// ```
+    pub fn symmetric_difference(&self, other: &Purse<S>) -> Purse<S> {
+        self.combined(other, SetOp::SymmetricDifference)
+    }
+    /// Checks whether every element of `self` is also in `other`, at least as many times.
+    ///
+    /// Elements of types without a recorded vtable can't be compared, so a purse holding any
+    /// is only a subset of a purse with no elements of that type if it has none itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value(1);
+    /// let mut b = Purse::new();
+    /// b.insert_value(1);
+    /// b.insert_value(1);
+    /// assert!(a.is_subset(&b));
+    /// assert!(!b.is_subset(&a));
+    /// ```
+    pub fn is_subset(&self, other: &Purse<S>) -> bool {
+        self.non_empty_buckets().all(|(type_id, ours)| {
+            let vtable = self.vtables.get(type_id).or(other.vtables.get(type_id));
+            match (vtable, other.data.get(type_id)) {
+                (Some(vtable), Some(theirs)) => (vtable.is_subset)(ours, theirs),
+                _ => false,
+            }
+        })
+    }
+    /// Checks whether `self` holds every element of `other`, at least as many times.
+    ///
+    /// This is the same as `other.is_subset(self)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut a = Purse::new();
+    /// a.insert_value("x");
+    /// a.insert_value("y");
+    /// let mut b = Purse::new();
+    /// b.insert_value("y");
+    /// assert!(a.is_superset(&b));
+    /// ```
+    pub fn is_superset(&self, other: &Purse<S>) -> bool {
+        other.is_subset(self)
+    }
+}
+
+macro_rules! impl_set_op {
+    ($op:ident, $method:ident, $assign:ident, $assign_method:ident, $set_op:expr, $name:ident) => {
+        impl<S: BuildHasher + Clone> $op<&Purse<S>> for &Purse<S> {
+            type Output = Purse<S>;
+
+            #[doc = concat!("See [`Purse::", stringify!($name), "`].")]
+            fn $method(self, other: &Purse<S>) -> Purse<S> {
+                self.combined(other, $set_op)
+            }
+        }
+
+        impl<S: BuildHasher + Clone> $assign<&Purse<S>> for Purse<S> {
+            fn $assign_method(&mut self, other: &Purse<S>) {
+                self.combine_with(other, $set_op);
+            }
+        }
+    };
+}
+
+impl_set_op!(BitOr, bitor, BitOrAssign, bitor_assign, SetOp::Union, union);
+impl_set_op!(Add, add, AddAssign, add_assign, SetOp::Sum, sum);
+impl_set_op!(
+    BitAnd,
+    bitand,
+    BitAndAssign,
+    bitand_assign,
+    SetOp::Intersection,
+    intersection
+);
+impl_set_op!(
+    Sub,
+    sub,
+    SubAssign,
+    sub_assign,
+    SetOp::Difference,
+    difference
+);
+impl_set_op!(
+    BitXor,
+    bitxor,
+    BitXorAssign,
+    bitxor_assign,
+    SetOp::SymmetricDifference,
+    symmetric_difference
+);
@@ -0,0 +1,293 @@
+//! A purse that keeps itself within a [`Quota`] by evicting elements.
+//!
+//! A [`BoundedPurse`] stores every element with an [`ItemHandle`], and keeps
+//! the handles of each type ordered by when their element was inserted or, for
+//! [`Eviction::LeastRecentlyUsed`], last accessed. After each insertion it
+//! evicts elements until the purse fits its quota again: from the inserted
+//! type while that type is over its own limit, and from any type otherwise.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::ops::Deref;
+
+use crate::handle::ErasedHandle;
+use crate::map::HashMap;
+use crate::{ItemHandle, Limit, Purse, Quota};
+
+/// Which element a [`BoundedPurse`] evicts when it goes over its quota.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Eviction {
+    /// Evicts the element inserted first.
+    #[default]
+    Oldest,
+    /// Evicts the element that was inserted or accessed least recently. Accesses
+    /// are calls to [`BoundedPurse::get_and_touch`],
+    /// [`BoundedPurse::get_mut_and_touch`] and [`BoundedPurse::touch`]. Reads of the
+    /// purse through `Deref`, such as [`Purse::get`], [`Purse::contains`] or
+    /// [`Purse::iter`], don't count.
+    LeastRecentlyUsed,
+    /// Evicts an element picked at random, each as likely as the others.
+    ///
+    /// The choice comes from a fast pseudo-random generator that an adversary
+    /// could predict, and takes time linear in the number of elements of the
+    /// evicted type.
+    Random,
+}
+
+/// The handles of the tracked elements of one type, by the tick of their
+/// insertion or last access.
+type Tracked = BTreeMap<u64, ErasedHandle>;
+
+/// The callback set with [`BoundedPurse::on_evict`].
+type OnEvict = Box<dyn FnMut(Box<dyn Any>)>;
+
+/// A purse that evicts elements to stay within a [`Quota`], for use as a cache of
+/// elements of any type.
+///
+/// Limits on the number of elements, per type or in total, and on their bytes are
+/// enforced after every insertion by evicting elements under an [`Eviction`] policy.
+/// Evicted elements are handed to the callback set with
+/// [`on_evict`](BoundedPurse::on_evict), or returned from
+/// [`insert`](BoundedPurse::insert) if there is none.
+///
+/// Everything that reads the purse is available through `Deref`, and elements can be
+/// reached individually through the [`ItemHandle`] returned on insertion. Only
+/// [`get_and_touch`](BoundedPurse::get_and_touch) and its siblings count as an access
+/// for [`Eviction::LeastRecentlyUsed`], whose eviction order ignores reads through
+/// `Deref`, including [`Purse::get`].
+///
+/// # Examples
+///
+/// ```
+/// # use purse::{BoundedPurse, Eviction, Quota};
+/// let quota = Quota {
+///     max_per_type: Some(2),
+///     ..Quota::default()
+/// };
+/// let mut cache = BoundedPurse::new(quota, Eviction::LeastRecentlyUsed);
+/// let (first, _) = cache.insert("first");
+/// cache.insert("second");
+/// cache.insert(1u8);
+///
+/// // Reading the first element makes the second the least recently used.
+/// assert_eq!(cache.get_and_touch(first), Some(&"first"));
+/// let (_, evicted) = cache.insert("third");
+/// assert_eq!(evicted.len(), 1);
+/// assert_eq!(evicted[0].downcast_ref::<&str>(), Some(&"second"));
+/// assert_eq!(cache.count::<&str>(), 2);
+/// assert_eq!(cache.count::<u8>(), 1);
+/// ```
+pub struct BoundedPurse {
+    purse: Purse,
+    quota: Quota,
+    eviction: Eviction,
+    types: HashMap<TypeId, Tracked>,
+    /// The tick of each element by the key of its handle.
+    ticks: HashMap<u64, u64>,
+    /// Counts insertions and accesses, ordering the elements of `types`.
+    clock: u64,
+    /// The state of the xorshift generator behind [`Eviction::Random`].
+    seed: u64,
+    on_evict: Option<OnEvict>,
+}
+
+impl BoundedPurse {
+    /// Creates an empty purse that keeps within `quota` by evicting under `eviction`.
+    pub fn new(quota: Quota, eviction: Eviction) -> Self {
+        Self {
+            purse: Purse::new(),
+            quota,
+            eviction,
+            types: HashMap::default(),
+            ticks: HashMap::default(),
+            clock: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+            on_evict: None,
+        }
+    }
+    /// Returns the limits the purse keeps to.
+    pub fn quota(&self) -> Quota {
+        self.quota
+    }
+    /// Returns the policy choosing which elements to evict.
+    pub fn eviction(&self) -> Eviction {
+        self.eviction
+    }
+    /// Hands every element evicted from now on to `f`, instead of returning them from
+    /// [`insert`](BoundedPurse::insert).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::{BoundedPurse, Eviction, Quota};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let quota = Quota {
+    ///     max_len: Some(1),
+    ///     ..Quota::default()
+    /// };
+    /// let mut purse = BoundedPurse::new(quota, Eviction::Oldest);
+    /// let evicted = Rc::new(RefCell::new(Vec::new()));
+    /// let sink = Rc::clone(&evicted);
+    /// purse.on_evict(move |elem| sink.borrow_mut().push(elem));
+    ///
+    /// purse.insert('a');
+    /// let (_, returned) = purse.insert(2u32);
+    /// assert!(returned.is_empty());
+    /// assert_eq!(evicted.borrow()[0].downcast_ref::<char>(), Some(&'a'));
+    /// ```
+    pub fn on_evict(&mut self, f: impl FnMut(Box<dyn Any>) + 'static) {
+        self.on_evict = Some(Box::new(f));
+    }
+    /// Inserts an element, then evicts elements until the purse is within its quota.
+    ///
+    /// While the type of the element is over [`Quota::max_per_type`], elements of that
+    /// type are evicted, and elements of any type while the purse is over another limit.
+    /// The new element can be evicted itself, if it is the only candidate or under
+    /// [`Eviction::Random`], in which case its handle refers to nothing.
+    ///
+    /// # Returns
+    /// A handle to the element, and the evicted elements, in the order they were evicted,
+    /// unless they went to the [`on_evict`](BoundedPurse::on_evict) callback.
+    pub fn insert<T: Any>(&mut self, elem: T) -> (ItemHandle<T>, Vec<Box<dyn Any>>) {
+        let handle = self.purse.insert_with_handle(elem);
+        let tick = self.tick();
+        self.types
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .insert(tick, ErasedHandle::new(handle));
+        self.ticks.insert(handle.key, tick);
+
+        let mut evicted = Vec::new();
+        while let Some(limit) = self.purse.exceeded_limit::<T>(&self.quota, 0) {
+            let scope = matches!(limit, Limit::PerType(_)).then(TypeId::of::<T>);
+            let Some(elem) = self.evict(scope) else {
+                break;
+            };
+            evicted.push(elem);
+        }
+        if let Some(on_evict) = &mut self.on_evict {
+            evicted.drain(..).for_each(on_evict);
+        }
+        (handle, evicted)
+    }
+    /// Returns the element `handle` refers to, counting as an access to it.
+    pub fn get_and_touch<T: Any>(&mut self, handle: ItemHandle<T>) -> Option<&T> {
+        self.touch(handle);
+        self.purse.get(handle)
+    }
+    /// Returns a mutable reference to the element `handle` refers to, counting as an
+    /// access to it.
+    pub fn get_mut_and_touch<T: Any>(&mut self, handle: ItemHandle<T>) -> Option<&mut T> {
+        self.touch(handle);
+        self.purse.get_mut(handle)
+    }
+    /// Marks the element `handle` refers to as accessed, returning `false` if it is no
+    /// longer in the purse.
+    ///
+    /// Only [`Eviction::LeastRecentlyUsed`] takes accesses into account.
+    pub fn touch<T: Any>(&mut self, handle: ItemHandle<T>) -> bool {
+        if self.purse.get(handle).is_none() {
+            return false;
+        }
+        if self.eviction == Eviction::LeastRecentlyUsed {
+            let tick = self.tick();
+            if let Some(order) = self.untrack::<T>(handle.key) {
+                order.insert(tick, ErasedHandle::new(handle));
+                self.ticks.insert(handle.key, tick);
+            }
+        }
+        true
+    }
+    /// Removes the element `handle` refers to.
+    pub fn remove_by_handle<T: Any>(&mut self, handle: ItemHandle<T>) -> Option<T> {
+        let elem = self.purse.remove_by_handle(handle)?;
+        self.untrack::<T>(handle.key);
+        Some(elem)
+    }
+    /// Removes all elements, without evicting them.
+    pub fn clear(&mut self) {
+        self.purse.clear();
+        self.types.clear();
+        self.ticks.clear();
+    }
+    /// Unwraps the underlying purse.
+    pub fn into_inner(self) -> Purse {
+        self.purse
+    }
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+    /// Stops tracking the element of type `T` whose handle has `key`, returning the
+    /// order of its type.
+    fn untrack<T: Any>(&mut self, key: u64) -> Option<&mut Tracked> {
+        let tick = self.ticks.remove(&key)?;
+        let order = self.types.get_mut(&TypeId::of::<T>())?;
+        order.remove(&tick);
+        Some(order)
+    }
+    /// Evicts an element of the type `scope`, or of any type if `None`, returning
+    /// `None` if there are no elements to evict.
+    fn evict(&mut self, scope: Option<TypeId>) -> Option<Box<dyn Any>> {
+        let (type_id, tick) = match self.eviction {
+            Eviction::Oldest | Eviction::LeastRecentlyUsed => self.first(scope)?,
+            Eviction::Random => self.random(scope)?,
+        };
+        let handle = self.types.get_mut(&type_id)?.remove(&tick)?;
+        self.ticks.remove(&handle.key);
+        handle.remove(&mut self.purse)
+    }
+    /// Returns the type and tick of the element with the lowest tick.
+    fn first(&self, scope: Option<TypeId>) -> Option<(TypeId, u64)> {
+        self.candidates(scope)
+            .filter_map(|(type_id, order)| Some((*type_id, *order.keys().next()?)))
+            .min_by_key(|(_, tick)| *tick)
+    }
+    /// Returns the type and tick of an element picked at random.
+    fn random(&mut self, scope: Option<TypeId>) -> Option<(TypeId, u64)> {
+        let len: usize = self.candidates(scope).map(|(_, order)| order.len()).sum();
+        if len == 0 {
+            return None;
+        }
+        // xorshift64, see Marsaglia's "Xorshift RNGs".
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        let mut n = (self.seed % len as u64) as usize;
+        for (type_id, order) in self.candidates(scope) {
+            match order.keys().nth(n) {
+                Some(tick) => return Some((*type_id, *tick)),
+                None => n -= order.len(),
+            }
+        }
+        None
+    }
+    fn candidates(&self, scope: Option<TypeId>) -> impl Iterator<Item = (&TypeId, &Tracked)> {
+        self.types
+            .iter()
+            .filter(move |(type_id, _)| scope.map_or(true, |scope| **type_id == scope))
+    }
+}
+
+impl Deref for BoundedPurse {
+    type Target = Purse;
+
+    fn deref(&self) -> &Purse {
+        &self.purse
+    }
+}
+
+impl fmt::Debug for BoundedPurse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedPurse")
+            .field("purse", &self.purse)
+            .field("quota", &self.quota)
+            .field("eviction", &self.eviction)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,113 @@
+//! Sources of time for purses whose contents age, such as [`ExpiringPurse`](crate::ExpiringPurse).
+//!
+//! A [`Clock`] reports time as the [`Duration`] elapsed since a point of its
+//! own choosing, which is all a purse needs to tell how long ago something
+//! happened and keeps the trait available without `std`. [`ManualClock`] only
+//! moves when told to, for tests and simulations.
+
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use core::cell::Cell;
+use core::time::Duration;
+
+/// A source of time.
+pub trait Clock {
+    /// Returns the time elapsed since a fixed point, such as the creation of the
+    /// clock. The time must never go backwards.
+    fn now(&self) -> Duration;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Rc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+/// The time on the system's monotonic clock, since the clock was created.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    /// Starts a clock that reads zero now.
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that stands still until it is moved by hand.
+///
+/// The clock is moved through a shared reference, so a purse can hold a
+/// reference to it, or an `Rc`, while the caller moves it.
+///
+/// # Examples
+///
+/// ```
+/// # use core::time::Duration;
+/// # use purse::{Clock, ManualClock};
+/// let clock = ManualClock::new();
+/// assert_eq!(clock.now(), Duration::ZERO);
+/// clock.advance(Duration::from_secs(5));
+/// assert_eq!((&clock).now(), Duration::from_secs(5));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a clock that reads zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get().saturating_add(by));
+    }
+    /// Sets the time the clock reads.
+    ///
+    /// # Panics
+    /// Panics if `now` is before the time the clock reads, since clocks never go
+    /// backwards.
+    pub fn set(&self, now: Duration) {
+        assert!(now >= self.now.get(), "a clock can't go backwards");
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
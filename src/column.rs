@@ -0,0 +1,703 @@
+//! Storage for all items of a single type.
+//!
+//! Each type in a [`Purse`](crate::Purse) gets its own [`Column`], chosen with
+//! a [`Strategy`] when the type is registered and a list otherwise. The
+//! purse keeps its columns behind the [`AnyColumn`] trait object so that
+//! columns of different types can live in the same map. Typed methods downcast
+//! the column once and then call into it, rather than downcasting every
+//! element.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Bound, RangeBounds};
+
+use crate::hashed::HashedBag;
+use crate::keyed::KeyedColumn;
+use crate::map::{self, HashSet};
+use crate::memory::TypeMemory;
+use crate::sequenced::SequencedColumn;
+use crate::sorted::SortedBag;
+use crate::{Bucket, RangeIter, TypeIter, TypeIterMut};
+
+/// Storage for the items of one type `T` in a [`Purse`](crate::Purse).
+///
+/// Every typed method of the purse dispatches to the column registered for its
+/// type. The crate provides columns for the strategies in [`Strategy`], and other
+/// backends can be plugged in by implementing this trait and registering them
+/// with [`Strategy::custom`].
+///
+/// A column only has to store, list and remove items. Lookups, counting and the
+/// order statistics behind methods such as [`Purse::min`](crate::Purse::min) have
+/// default implementations that scan [`iter`](Column::iter), which a column can
+/// override when it knows a faster way. Methods that compare items are only
+/// called when `T` supports the comparison.
+///
+/// # Examples
+///
+/// ```
+/// # use purse::{Column, Purse, Strategy, TypeIter};
+/// /// Keeps only the most recent item.
+/// #[derive(Default)]
+/// struct Latest<T>(Option<T>);
+///
+/// impl<T: 'static> Column<T> for Latest<T> {
+///     fn len(&self) -> usize {
+///         self.0.iter().len()
+///     }
+///     fn insert(&mut self, value: T) {
+///         self.0 = Some(value);
+///     }
+///     fn iter(&self) -> TypeIter<'_, T> {
+///         TypeIter::new(self.0.iter())
+///     }
+///     fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+///         if limit > 0 && self.0.as_ref().is_some_and(matches) {
+///             self.0 = None;
+///             return 1;
+///         }
+///         0
+///     }
+///     fn pop(&mut self) -> Option<T> {
+///         self.0.take()
+///     }
+/// }
+///
+/// let mut purse = Purse::new();
+/// purse.register::<u8>(Strategy::custom::<Latest<u8>>());
+/// purse.insert(1u8);
+/// purse.insert(2u8);
+/// assert_eq!(purse.count::<u8>(), 1);
+/// assert!(purse.contains(2u8));
+/// ```
+pub trait Column<T> {
+    /// Returns the number of items, counting duplicates.
+    fn len(&self) -> usize;
+
+    /// Checks if the column holds no items.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stores an item.
+    fn insert(&mut self, value: T);
+
+    /// Stores `n` copies of an item.
+    ///
+    /// The default implementation inserts `n - 1` clones followed by `value`.
+    fn insert_n(&mut self, value: T, n: u64)
+    where
+        T: Clone,
+    {
+        if n == 0 {
+            return;
+        }
+        for _ in 1..n {
+            self.insert(value.clone());
+        }
+        self.insert(value);
+    }
+
+    /// Iterates over every item, yielding duplicates as many times as they are
+    /// stored.
+    fn iter(&self) -> TypeIter<'_, T>;
+
+    /// Removes up to `limit` items for which `matches` returns `true`, returning
+    /// how many were removed.
+    ///
+    /// Columns that store equal items only once may call `matches` once for all
+    /// of them.
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64;
+
+    /// Removes and returns an item, or `None` if the column is empty.
+    fn pop(&mut self) -> Option<T>;
+
+    /// Removes and returns every item.
+    ///
+    /// The default implementation pops items until the column is empty, and
+    /// returns them in the reverse of the order they were popped in.
+    fn take_all(&mut self) -> Vec<T> {
+        let mut elems = Vec::with_capacity(self.len());
+        while let Some(elem) = self.pop() {
+            elems.push(elem);
+        }
+        elems.reverse();
+        elems
+    }
+
+    /// Checks if an item equal to `value` is stored.
+    fn contains(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.iter().any(|elem| elem == value)
+    }
+
+    /// Counts the stored items equal to `value`.
+    fn count_of(&self, value: &T) -> u64
+    where
+        T: PartialEq,
+    {
+        self.iter().filter(|elem| *elem == value).count() as u64
+    }
+
+    /// Removes up to `n` items equal to `value`, returning how many were
+    /// removed.
+    fn remove_n(&mut self, value: &T, n: u64) -> u64
+    where
+        T: PartialEq,
+    {
+        self.remove_where(&mut |elem| elem == value, n)
+    }
+
+    /// Returns the items as a contiguous slice, if they are stored that way.
+    fn as_slice(&self) -> Option<&[T]> {
+        None
+    }
+
+    /// Returns the items as a contiguous mutable slice, if they are stored that
+    /// way and can be changed in place.
+    fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        None
+    }
+
+    /// Returns an iterator over mutable references to the items, if they can be
+    /// changed in place.
+    ///
+    /// Defaults to iterating over [`as_mut_slice`](Column::as_mut_slice).
+    fn iter_mut(&mut self) -> Option<TypeIterMut<'_, T>> {
+        self.as_mut_slice().map(TypeIterMut::from_slice)
+    }
+
+    /// Returns the smallest item.
+    fn min(&self) -> Option<&T>
+    where
+        T: Ord,
+    {
+        self.iter().min()
+    }
+
+    /// Returns the largest item.
+    fn max(&self) -> Option<&T>
+    where
+        T: Ord,
+    {
+        self.iter().max()
+    }
+
+    /// Iterates in ascending order over the items within `range`.
+    ///
+    /// The purse never passes a range whose start lies after its end.
+    fn range(&self, range: (Bound<&T>, Bound<&T>)) -> RangeIter<'_, T>
+    where
+        T: Ord,
+    {
+        let mut elems: Vec<&T> = self.iter().filter(|elem| range.contains(*elem)).collect();
+        elems.sort();
+        RangeIter::from_sorted(elems)
+    }
+
+    /// Counts the items strictly less than `value`.
+    fn rank(&self, value: &T) -> u64
+    where
+        T: Ord,
+    {
+        self.iter().filter(|elem| *elem < value).count() as u64
+    }
+
+    /// Returns the item at position `n` in ascending order, counting from zero.
+    fn nth_smallest(&self, n: u64) -> Option<&T>
+    where
+        T: Ord,
+    {
+        let mut elems: Vec<&T> = self.iter().collect();
+        let n = usize::try_from(n).ok().filter(|&n| n < elems.len())?;
+        Some(*elems.select_nth_unstable(n).1)
+    }
+
+    /// Returns how many items the column can hold without allocating again.
+    ///
+    /// The default implementation returns [`len`](Column::len).
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    /// Makes room for at least `additional` more items.
+    ///
+    /// The default implementation does nothing, for columns that don't allocate
+    /// ahead.
+    fn reserve(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
+    /// Frees the room the column holds beyond its items.
+    ///
+    /// The default implementation does nothing.
+    fn shrink_to_fit(&mut self) {}
+
+    /// Returns the number of bytes the column has allocated, including room for
+    /// items it doesn't hold yet and bookkeeping such as counts and keys.
+    ///
+    /// Memory owned by the items themselves, such as the contents of a `String`,
+    /// isn't counted. The default implementation counts
+    /// [`capacity`](Column::capacity) items of `T`.
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>()
+    }
+
+    /// Returns the column as `Any`, so that code holding a `dyn Column<T>` can
+    /// recover its concrete type.
+    ///
+    /// The purse uses this to take faster paths for some of its own columns.
+    /// Other columns can keep the default, which returns `None`.
+    fn as_any(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    /// Mutable counterpart of [`as_any`](Column::as_any).
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+}
+
+/// Keeps every item in a `Vec<T>`, in insertion order. This is the default
+/// column.
+///
+/// The built-in columns wrap their collections rather than implementing
+/// [`Column`] on them directly, so that bringing the trait into scope does not
+/// change which `contains` or `iter` a plain `Vec` resolves to.
+pub(crate) struct ListColumn<T>(pub(crate) Vec<T>);
+
+impl<T> Default for ListColumn<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T: Any> Column<T> for ListColumn<T> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn insert(&mut self, value: T) {
+        self.0.push(value);
+    }
+    fn insert_n(&mut self, value: T, n: u64)
+    where
+        T: Clone,
+    {
+        if n == 0 {
+            return;
+        }
+        self.0
+            .reserve(usize::try_from(n).expect("the copies don't fit in memory"));
+        for _ in 1..n {
+            self.0.push(value.clone());
+        }
+        self.0.push(value);
+    }
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::from_slice(&self.0)
+    }
+    /// Removes matching items in insertion order, keeping the order of the rest.
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        self.0.retain(|elem| {
+            if removed < limit && matches(elem) {
+                removed += 1;
+                return false;
+            }
+            true
+        });
+        removed
+    }
+    /// Removes the most recently inserted item.
+    fn pop(&mut self) -> Option<T> {
+        self.0.pop()
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        core::mem::take(&mut self.0)
+    }
+    fn contains(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.0.contains(value)
+    }
+    fn as_slice(&self) -> Option<&[T]> {
+        Some(&self.0)
+    }
+    fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        Some(&mut self.0)
+    }
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+/// Keeps each distinct value once in a `HashSet<T>`, ignoring duplicates on
+/// insertion.
+pub(crate) struct SetColumn<T>(HashSet<T>);
+
+impl<T> Default for SetColumn<T> {
+    fn default() -> Self {
+        Self(HashSet::default())
+    }
+}
+
+impl<T: Any + Hash + Eq + Clone> Column<T> for SetColumn<T> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn insert(&mut self, value: T) {
+        self.0.insert(value);
+    }
+    fn insert_n(&mut self, value: T, n: u64) {
+        if n > 0 {
+            self.0.insert(value);
+        }
+    }
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::new(self.0.iter())
+    }
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        self.0.retain(|elem| {
+            if removed < limit && matches(elem) {
+                removed += 1;
+                return false;
+            }
+            true
+        });
+        removed
+    }
+    /// Removes an arbitrary item.
+    fn pop(&mut self) -> Option<T> {
+        let value = self.0.iter().next()?.clone();
+        self.0.take(&value)
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        core::mem::take(&mut self.0).into_iter().collect()
+    }
+    fn contains(&self, value: &T) -> bool {
+        self.0.contains(value)
+    }
+    fn count_of(&self, value: &T) -> u64 {
+        u64::from(self.0.contains(value))
+    }
+    fn remove_n(&mut self, value: &T, n: u64) -> u64 {
+        u64::from(n > 0 && self.0.remove(value))
+    }
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+    fn heap_size(&self) -> usize {
+        map::table_size::<T>(self.0.capacity())
+    }
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+/// How the items of one type are stored, passed to
+/// [`Purse::register`](crate::Purse::register).
+///
+/// # Examples
+///
+/// ```
+/// # use purse::{Purse, Strategy};
+/// let mut purse = Purse::new();
+/// purse.register::<&str>(Strategy::hashed());
+/// purse.register::<u32>(Strategy::ordered());
+/// purse.register::<char>(Strategy::set());
+///
+/// purse.insert_n('x', 3);
+/// assert_eq!(purse.count::<char>(), 1);
+/// ```
+pub struct Strategy<T> {
+    make: fn() -> Bucket,
+    /// Creates the bucket used instead in an insertion-ordered purse, for the
+    /// strategies that keep individual items.
+    sequenced: Option<fn() -> Bucket>,
+    /// Whether the columns are `Send + Sync` whenever `T` is, which the
+    /// thread-safe purses require.
+    send_sync: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Strategy<T> {
+    pub(crate) fn new(make: fn() -> Bucket) -> Self {
+        Self {
+            make,
+            sequenced: None,
+            send_sync: true,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns whether the strategy may be registered with a
+    /// [`SendPurse`](crate::SendPurse) for a `Send + Sync` type.
+    pub(crate) fn is_send_sync(&self) -> bool {
+        self.send_sync
+    }
+
+    /// Returns the function creating an empty bucket for the strategy, in a purse
+    /// that is insertion-ordered or not.
+    pub(crate) fn factory(&self, insertion_ordered: bool) -> fn() -> Bucket {
+        match self.sequenced {
+            Some(sequenced) if insertion_ordered => sequenced,
+            _ => self.make,
+        }
+    }
+}
+
+impl<T: Any> Strategy<T> {
+    /// Keeps every item in a list, in insertion order.
+    ///
+    /// This is how types are stored until they are registered otherwise.
+    pub fn list() -> Self {
+        Self {
+            sequenced: Some(Bucket::sequenced::<T>),
+            ..Self::new(Bucket::list::<T>)
+        }
+    }
+
+    /// Keeps items in slots that [`ItemHandle`](crate::ItemHandle)s can point at.
+    /// See [`Purse::insert_with_handle`](crate::Purse::insert_with_handle).
+    pub fn keyed() -> Self {
+        Self {
+            sequenced: Some(|| Bucket::new(Box::new(KeyedColumn::<T>::sequenced()))),
+            ..Self::new(|| Bucket::new(Box::<KeyedColumn<T>>::default()))
+        }
+    }
+
+    /// Stores items in a column of type `C`, created with `C::default()`.
+    ///
+    /// The strategy can only be registered with a plain [`Purse`](crate::Purse). Use
+    /// [`custom_send`](Strategy::custom_send) for a column that can be registered with a
+    /// [`SendPurse`](crate::SendPurse) too.
+    pub fn custom<C: Column<T> + Default + 'static>() -> Self {
+        Self {
+            send_sync: false,
+            ..Self::new(|| Bucket::new(Box::<C>::default()))
+        }
+    }
+
+    /// Like [`custom`](Strategy::custom), for a column that is `Send` and `Sync`, so that
+    /// the strategy can also be registered with a [`SendPurse`](crate::SendPurse) or a
+    /// `ConcurrentPurse`.
+    pub fn custom_send<C: Column<T> + Default + Send + Sync + 'static>() -> Self {
+        Self::new(|| Bucket::new(Box::<C>::default()))
+    }
+}
+
+impl<T: Any + Hash + Eq + Clone> Strategy<T> {
+    /// Keeps each distinct value once, along with the number of times it was
+    /// inserted. See [`Purse::register_hashed`](crate::Purse::register_hashed).
+    pub fn hashed() -> Self {
+        Self::new(|| Bucket::new(Box::<HashedBag<T>>::default()))
+    }
+
+    /// Like [`hashed`](Strategy::hashed), but hashes the values with hashers built by
+    /// `S` rather than std's `RandomState`, or hashbrown's default hasher without
+    /// the `std` feature.
+    ///
+    /// Lookups through [`Purse::contains_borrowed`](crate::Purse::contains_borrowed) and
+    /// [`Purse::remove_borrowed`](crate::Purse::remove_borrowed) only use the hash index
+    /// with the default hasher, and scan the values otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::{Purse, Strategy};
+    /// use std::hash::BuildHasherDefault;
+    /// use std::collections::hash_map::DefaultHasher;
+    ///
+    /// let mut purse = Purse::new();
+    /// purse.register::<u64>(Strategy::hashed_with_hasher::<BuildHasherDefault<DefaultHasher>>());
+    /// purse.insert_n(7u64, 3);
+    /// assert_eq!(purse.count_of(&7u64), 3);
+    /// ```
+    pub fn hashed_with_hasher<S>() -> Self
+    where
+        S: BuildHasher + Default + Send + Sync + 'static,
+    {
+        Self::new(|| Bucket::new(Box::<HashedBag<T, S>>::default()))
+    }
+
+    /// Keeps each distinct value once, and ignores inserting it again.
+    ///
+    /// Unlike the other strategies, this changes what the purse holds: the count
+    /// of any value is at most one.
+    pub fn set() -> Self {
+        Self::new(|| Bucket::new(Box::<SetColumn<T>>::default()))
+    }
+}
+
+impl<T: Any + Ord + Clone> Strategy<T> {
+    /// Keeps each distinct value once, in ascending order, along with the number
+    /// of times it was inserted. See
+    /// [`Purse::register_ordered`](crate::Purse::register_ordered).
+    pub fn ordered() -> Self {
+        Self::new(|| Bucket::new(Box::<SortedBag<T>>::default()))
+    }
+}
+
+impl<T> Clone for Strategy<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Strategy<T> {}
+
+impl<T> fmt::Debug for Strategy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Strategy")
+            .field("type", &core::any::type_name::<T>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Type-erased view over a `Box<dyn Column<T>>`.
+pub(crate) trait AnyColumn {
+    fn len(&self) -> usize;
+    /// Returns the size of an item.
+    fn value_size(&self) -> usize;
+    fn type_name(&self) -> &'static str;
+    fn iter_any(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_>;
+    fn iter_mut_any(&mut self) -> Box<dyn Iterator<Item = &mut dyn Any> + '_>;
+    /// Returns the item at `index` of a column kept in a slice.
+    fn get_any(&self, index: usize) -> Option<&dyn Any>;
+    /// Iterates over the items of a keyed column along with the keys of their handles.
+    fn iter_keyed_any(&self) -> Option<Box<dyn Iterator<Item = (u64, &dyn Any)> + '_>>;
+    /// Returns the sequence numbers of a column in an insertion-ordered purse, in
+    /// the order of its items.
+    fn sequence(&self) -> Option<Cow<'_, [u64]>>;
+    /// Returns the item with the lowest sequence number and that number.
+    fn oldest(&self) -> Option<(u64, &dyn Any)>;
+    /// Returns the item with the highest sequence number and that number.
+    fn newest(&self) -> Option<(u64, &dyn Any)>;
+    fn shrink_to_fit(&mut self);
+    /// Reports the memory held by the column and its boxes, leaving the map
+    /// entries for the purse to fill in.
+    fn memory_usage(&self) -> TypeMemory;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_boxed_iter(self: Box<Self>) -> Box<dyn Iterator<Item = Box<dyn Any>>>;
+}
+
+// Both traits have methods named `len` and `as_any`, so calls into the column
+// below go through `Column::` explicitly.
+impl<T: Any> AnyColumn for Box<dyn Column<T>> {
+    fn len(&self) -> usize {
+        Column::len(&**self)
+    }
+    fn value_size(&self) -> usize {
+        mem::size_of::<T>()
+    }
+    fn type_name(&self) -> &'static str {
+        core::any::type_name::<T>()
+    }
+    fn iter_any(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        Box::new(Column::iter(&**self).map(|v| v as &dyn Any))
+    }
+    fn iter_mut_any(&mut self) -> Box<dyn Iterator<Item = &mut dyn Any> + '_> {
+        match Column::iter_mut(&mut **self) {
+            Some(elems) => Box::new(elems.map(|v| v as &mut dyn Any)),
+            None => Box::new(core::iter::empty()),
+        }
+    }
+    fn get_any(&self, index: usize) -> Option<&dyn Any> {
+        let elems = Column::as_slice(&**self)?;
+        elems.get(index).map(|v| v as &dyn Any)
+    }
+    fn iter_keyed_any(&self) -> Option<Box<dyn Iterator<Item = (u64, &dyn Any)> + '_>> {
+        let column = Column::as_any(&**self)?.downcast_ref::<KeyedColumn<T>>()?;
+        Some(Box::new(
+            column.iter_keyed().map(|(key, v)| (key, v as &dyn Any)),
+        ))
+    }
+    fn sequence(&self) -> Option<Cow<'_, [u64]>> {
+        let column = Column::as_any(&**self)?;
+        if let Some(list) = column.downcast_ref::<SequencedColumn<T>>() {
+            return Some(Cow::Borrowed(list.sequence()));
+        }
+        column
+            .downcast_ref::<KeyedColumn<T>>()?
+            .sequence()
+            .map(Cow::Owned)
+    }
+    fn oldest(&self) -> Option<(u64, &dyn Any)> {
+        let column = Column::as_any(&**self)?;
+        if let Some(list) = column.downcast_ref::<SequencedColumn<T>>() {
+            let items = list.as_slice()?;
+            return Some((*list.sequence().first()?, items.first()?));
+        }
+        let (n, item) = column.downcast_ref::<KeyedColumn<T>>()?.oldest()?;
+        Some((n, item))
+    }
+    fn newest(&self) -> Option<(u64, &dyn Any)> {
+        let column = Column::as_any(&**self)?;
+        if let Some(list) = column.downcast_ref::<SequencedColumn<T>>() {
+            let items = list.as_slice()?;
+            return Some((*list.sequence().last()?, items.last()?));
+        }
+        let (n, item) = column.downcast_ref::<KeyedColumn<T>>()?.newest()?;
+        Some((n, item))
+    }
+    fn shrink_to_fit(&mut self) {
+        Column::shrink_to_fit(&mut **self);
+    }
+    fn memory_usage(&self) -> TypeMemory {
+        TypeMemory {
+            type_id: TypeId::of::<T>(),
+            type_name: core::any::type_name::<T>(),
+            len: Column::len(&**self),
+            values: Column::heap_size(&**self),
+            boxes: mem::size_of::<Self>() + mem::size_of_val(&**self),
+            map_entries: 0,
+        }
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn into_boxed_iter(mut self: Box<Self>) -> Box<dyn Iterator<Item = Box<dyn Any>>> {
+        let elems = Column::take_all(&mut **self);
+        Box::new(elems.into_iter().map(|v| Box::new(v) as Box<dyn Any>))
+    }
+}
+
+impl fmt::Debug for dyn AnyColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Column").field("len", &self.len()).finish()
+    }
+}
@@ -0,0 +1,68 @@
+//! Storage strategies that keep each distinct value once, together with its
+//! multiplicity.
+//!
+//! Both [`HashedBag`](crate::hashed::HashedBag) and
+//! [`SortedBag`](crate::sorted::SortedBag) work this way and differ only in
+//! the map they keep their counts in. This module holds what they share: an
+//! iterator that expands `(value, count)` entries back into individual items.
+
+use core::iter::FusedIterator;
+
+/// Iterator over the items of a counted bag, repeating each value of the
+/// underlying `(value, count)` entries by its count.
+#[derive(Clone, Debug)]
+pub(crate) struct Iter<'a, T, I> {
+    entries: I,
+    current: Option<(&'a T, u64)>,
+    remaining: u64,
+}
+
+impl<'a, T, I> Iter<'a, T, I> {
+    /// Creates an iterator over `entries`, which must hold `len` items in
+    /// total.
+    pub(crate) fn new(entries: I, len: u64) -> Self {
+        Self {
+            entries,
+            current: None,
+            remaining: len,
+        }
+    }
+}
+
+impl<'a, T, I: Iterator<Item = (&'a T, &'a u64)>> Iterator for Iter<'a, T, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((value, n)) = &mut self.current {
+                if *n > 0 {
+                    *n -= 1;
+                    self.remaining -= 1;
+                    return Some(*value);
+                }
+            }
+            let (value, &n) = self.entries.next()?;
+            self.current = Some((value, n));
+        }
+    }
+
+    /// Only exact while the remaining items fit in a `usize`.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining) {
+            Ok(n) => (n, Some(n)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+impl<'a, T, I: Iterator<Item = (&'a T, &'a u64)>> ExactSizeIterator for Iter<'a, T, I> {}
+
+impl<'a, T, I: Iterator<Item = (&'a T, &'a u64)>> FusedIterator for Iter<'a, T, I> {}
+
+/// Yields `value` `n` times, cloning it for all but the last copy.
+pub(crate) fn repeat_owned<T: Clone>(value: T, n: u64) -> impl Iterator<Item = T> {
+    let mut value = Some(value);
+    (0..n)
+        .rev()
+        .filter_map(move |i| if i == 0 { value.take() } else { value.clone() })
+}
@@ -0,0 +1,24 @@
+//! Process-wide counters for numbers that are never handed out twice, such as
+//! the keys of [`ItemHandle`](crate::ItemHandle)s and the sequence numbers of
+//! insertion-ordered purses.
+
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_has_atomic = "64"))]
+use portable_atomic::{AtomicU64, Ordering};
+
+/// A counter shared by all threads that returns increasing numbers.
+///
+/// Targets without 64-bit atomics count with `portable-atomic`'s lock-based
+/// `AtomicU64`, so the numbers are 64 bits everywhere and never wrap in
+/// practice.
+pub(crate) struct Counter(AtomicU64);
+
+impl Counter {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+    pub(crate) fn next(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
+}
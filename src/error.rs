@@ -0,0 +1,34 @@
+//! Errors returned by fallible [`Purse`](crate::Purse) operations.
+
+use core::fmt;
+
+use crate::quota::Limit;
+
+/// An error from a fallible operation on a [`Purse`](crate::Purse).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PurseError {
+    /// Inserting would take the purse past a limit of its [`Quota`](crate::Quota).
+    QuotaExceeded {
+        /// The type of the element that was not inserted.
+        type_name: &'static str,
+        /// The limit that would have been exceeded.
+        limit: Limit,
+    },
+}
+
+impl fmt::Display for PurseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurseError::QuotaExceeded { type_name, limit } => {
+                write!(
+                    f,
+                    "inserting `{type_name}` would exceed the limit of {limit}"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PurseError {}
@@ -0,0 +1,252 @@
+//! A purse whose elements can be given a time to live.
+//!
+//! An [`ExpiringPurse`] stores elements with a time to live under an
+//! [`ItemHandle`], and keeps the handles ordered by deadline, so that
+//! [`ExpiringPurse::expire`] only visits the elements it removes. Until a
+//! sweep runs, expired elements stay in the purse, and reads can be set to
+//! skip them.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt;
+use core::ops::Deref;
+use core::time::Duration;
+
+use crate::clock::Clock;
+#[cfg(feature = "std")]
+use crate::clock::SystemClock;
+use crate::handle::ErasedHandle;
+use crate::map::HashMap;
+use crate::{ItemHandle, Purse};
+
+/// A purse whose elements can expire after a time to live, measured by a [`Clock`].
+///
+/// Elements inserted with [`insert_with_ttl`](ExpiringPurse::insert_with_ttl) expire once
+/// their time to live has passed, and are removed by the next
+/// [`expire`](ExpiringPurse::expire). Elements inserted with
+/// [`insert`](ExpiringPurse::insert) never expire.
+///
+/// Everything that reads the purse is available through `Deref`, but only the methods
+/// listed below know about deadlines.
+///
+/// # Hidden elements
+///
+/// Expired elements that haven't been swept are visible by default. With
+/// [`set_hide_expired`](ExpiringPurse::set_hide_expired), exactly these methods skip them,
+/// at the cost of looking at every expired element:
+///
+/// - [`len`](ExpiringPurse::len) and [`is_empty`](ExpiringPurse::is_empty)
+/// - [`count`](ExpiringPurse::count), [`count_of`](ExpiringPurse::count_of) and
+///   [`contains`](ExpiringPurse::contains)
+/// - [`iter`](ExpiringPurse::iter)
+/// - [`get`](ExpiringPurse::get)
+///
+/// Every other read goes through `Deref` to the [`Purse`] and still sees expired elements,
+/// for example [`Purse::iter_of_type`], [`Purse::get_all_of_type`], [`Purse::min`] and
+/// [`Purse::max`], the `Debug` output, and the methods above when called as [`Purse`]
+/// methods, such as `Purse::len(&purse)`. Call
+/// [`expire`](ExpiringPurse::expire) first to have those reads skip them too.
+///
+/// # Examples
+///
+/// ```
+/// # use core::time::Duration;
+/// # use purse::{ExpiringPurse, ManualClock};
+/// let clock = ManualClock::new();
+/// let mut purse = ExpiringPurse::with_clock(&clock);
+/// purse.insert_with_ttl("session", Duration::from_secs(60));
+/// purse.insert_with_ttl(7u32, Duration::from_secs(10));
+/// purse.insert(7u32);
+///
+/// clock.advance(Duration::from_secs(30));
+/// assert_eq!(purse.count::<u32>(), 2);
+/// purse.set_hide_expired(true);
+/// assert_eq!(purse.count::<u32>(), 1);
+///
+/// let expired = purse.expire();
+/// assert_eq!(expired.len(), 1);
+/// assert_eq!(expired[0].downcast_ref::<u32>(), Some(&7));
+/// assert!(purse.contains("session"));
+/// ```
+pub struct ExpiringPurse<C> {
+    purse: Purse,
+    clock: C,
+    hide_expired: bool,
+    /// The handles of the elements with a time to live, by deadline and the key of
+    /// the handle.
+    deadlines: BTreeMap<(Duration, u64), ErasedHandle>,
+    /// The deadline of each element by the key of its handle.
+    keys: HashMap<u64, Duration>,
+}
+
+#[cfg(feature = "std")]
+impl ExpiringPurse<SystemClock> {
+    /// Creates an empty purse that measures time with a [`SystemClock`].
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for ExpiringPurse<SystemClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> ExpiringPurse<C> {
+    /// Creates an empty purse that measures time with `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        Self {
+            purse: Purse::new(),
+            clock,
+            hide_expired: false,
+            deadlines: BTreeMap::new(),
+            keys: HashMap::default(),
+        }
+    }
+    /// Returns the clock of the purse.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+    /// Sets whether reads of the purse skip expired elements that haven't been swept
+    /// yet. They don't by default.
+    pub fn set_hide_expired(&mut self, hide: bool) {
+        self.hide_expired = hide;
+    }
+    /// Returns whether reads of the purse skip expired elements.
+    pub fn hides_expired(&self) -> bool {
+        self.hide_expired
+    }
+    /// Inserts an element that never expires.
+    pub fn insert<T: Any>(&mut self, elem: T) {
+        self.purse.insert(elem);
+    }
+    /// Inserts an element that expires once `ttl` has passed, returning a handle to it.
+    ///
+    /// A time to live of zero expires the element right away, and one too long for the
+    /// clock to reach never expires it.
+    pub fn insert_with_ttl<T: Any>(&mut self, elem: T, ttl: Duration) -> ItemHandle<T> {
+        let handle = self.purse.insert_with_handle(elem);
+        if let Some(deadline) = self.clock.now().checked_add(ttl) {
+            self.deadlines
+                .insert((deadline, handle.key), ErasedHandle::new(handle));
+            self.keys.insert(handle.key, deadline);
+        }
+        handle
+    }
+    /// Removes the expired elements and returns them, from the earliest deadline on.
+    pub fn expire(&mut self) -> Vec<Box<dyn Any>> {
+        let now = self.clock.now();
+        let mut expired = Vec::new();
+        while let Some(entry) = self.deadlines.first_entry() {
+            let (deadline, key) = *entry.key();
+            if deadline > now {
+                break;
+            }
+            let handle = entry.remove();
+            self.keys.remove(&key);
+            expired.extend(handle.remove(&mut self.purse));
+        }
+        expired
+    }
+    /// Returns how long until the element `handle` refers to expires, zero if it has
+    /// expired, or `None` if it never expires or is no longer in the purse.
+    pub fn expires_in<T: Any>(&self, handle: ItemHandle<T>) -> Option<Duration> {
+        self.purse.get(handle)?;
+        let deadline = self.keys.get(&handle.key)?;
+        Some(deadline.saturating_sub(self.clock.now()))
+    }
+    /// Returns the element `handle` refers to, unless it expired and expired elements
+    /// are hidden.
+    pub fn get<T: Any>(&self, handle: ItemHandle<T>) -> Option<&T> {
+        if self.hide_expired && self.expires_in(handle) == Some(Duration::ZERO) {
+            return None;
+        }
+        self.purse.get(handle)
+    }
+    /// Removes the element `handle` refers to, whether or not it expired.
+    pub fn remove_by_handle<T: Any>(&mut self, handle: ItemHandle<T>) -> Option<T> {
+        let elem = self.purse.remove_by_handle(handle)?;
+        if let Some(deadline) = self.keys.remove(&handle.key) {
+            self.deadlines.remove(&(deadline, handle.key));
+        }
+        Some(elem)
+    }
+    /// Returns the number of elements in the purse, see [`Purse::len`].
+    pub fn len(&self) -> usize {
+        self.purse.len() - self.hidden().count()
+    }
+    /// Returns `true` if the purse has no elements, see [`len`](ExpiringPurse::len).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Counts the elements of type `T`, see [`Purse::count`].
+    pub fn count<T: Any>(&self) -> u64 {
+        let hidden = self.hidden_of::<T>().count();
+        self.purse.count::<T>() - hidden as u64
+    }
+    /// Counts the elements equal to `elem`, see [`Purse::count_of`].
+    pub fn count_of<T: Any + Eq>(&self, elem: &T) -> u64 {
+        let hidden = self.hidden_of::<T>().filter(|e| *e == elem).count();
+        self.purse.count_of(elem) - hidden as u64
+    }
+    /// Checks if the purse contains an element equal to `t`, see [`Purse::contains`].
+    pub fn contains<T: Any + Eq>(&self, t: T) -> bool {
+        self.count_of(&t) > 0
+    }
+    /// Iterates over the elements of the purse, see [`Purse::iter`].
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        if !self.hide_expired {
+            return self.purse.iter();
+        }
+        let hidden = self.hidden_handles().map(|handle| handle.key).collect();
+        self.purse.iter_without(hidden)
+    }
+    /// Removes all elements, expired or not.
+    pub fn clear(&mut self) {
+        self.purse.clear();
+        self.deadlines.clear();
+        self.keys.clear();
+    }
+    /// Unwraps the underlying purse, which keeps the expired elements.
+    pub fn into_inner(self) -> Purse {
+        self.purse
+    }
+    /// Returns the handles of the expired elements that reads skip.
+    fn hidden_handles(&self) -> impl Iterator<Item = &ErasedHandle> + '_ {
+        let now = self.hide_expired.then(|| self.clock.now());
+        self.deadlines
+            .iter()
+            .take_while(move |((deadline, _), _)| now.is_some_and(|now| *deadline <= now))
+            .map(|(_, handle)| handle)
+    }
+    /// Returns the expired elements that reads skip.
+    fn hidden(&self) -> impl Iterator<Item = &dyn Any> + '_ {
+        self.hidden_handles()
+            .filter_map(|handle| handle.get(&self.purse))
+    }
+    fn hidden_of<T: Any>(&self) -> impl Iterator<Item = &T> + '_ {
+        self.hidden().filter_map(|elem| elem.downcast_ref())
+    }
+}
+
+impl<C> Deref for ExpiringPurse<C> {
+    type Target = Purse;
+
+    fn deref(&self) -> &Purse {
+        &self.purse
+    }
+}
+
+impl<C> fmt::Debug for ExpiringPurse<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpiringPurse")
+            .field("purse", &self.purse)
+            .field("hide_expired", &self.hide_expired)
+            .field("deadlines", &self.deadlines.len())
+            .finish_non_exhaustive()
+    }
+}
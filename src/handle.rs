@@ -0,0 +1,241 @@
+//! Handles to individual items of a [`Purse`].
+//!
+//! Equal items are interchangeable to every other method of the purse, so
+//! [`Purse::remove`] takes out whichever equal item it finds first. Items
+//! inserted with [`Purse::insert_with_handle`] are stored in a keyed column,
+//! and the returned [`ItemHandle`] reaches that one item in constant time.
+
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::marker::PhantomData;
+
+use crate::keyed::KeyedColumn;
+use crate::map::HashSet;
+use crate::{Bucket, Column, Purse, Strategy};
+
+/// Refers to one specific item of type `T` in a [`Purse`](crate::Purse), even
+/// when equal items sit next to it.
+///
+/// Created by [`Purse::insert_with_handle`](crate::Purse::insert_with_handle).
+/// A handle stops referring to anything once its item is removed, the purse is
+/// cleared, or the storage of `T` is changed with
+/// [`Purse::register`](crate::Purse::register). Clones of a purse hold copies of
+/// the items, which the handles of the original don't refer to.
+pub struct ItemHandle<T> {
+    pub(crate) index: usize,
+    pub(crate) key: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ItemHandle<T> {
+    pub(crate) fn new(index: usize, key: u64) -> Self {
+        Self {
+            index,
+            key,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for ItemHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ItemHandle<T> {}
+
+impl<T> PartialEq for ItemHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.key == other.key
+    }
+}
+
+impl<T> Eq for ItemHandle<T> {}
+
+impl<T> Hash for ItemHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.key.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for ItemHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ItemHandle")
+            .field("type", &core::any::type_name::<T>())
+            .field("index", &self.index)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+fn keyed<T: Any>(column: &dyn Column<T>) -> Option<&KeyedColumn<T>> {
+    column.as_any()?.downcast_ref()
+}
+
+/// An [`ItemHandle`] whose type is erased, for wrappers that keep the handles of
+/// elements of many types side by side.
+#[derive(Clone, Copy)]
+pub(crate) struct ErasedHandle {
+    pub(crate) index: usize,
+    pub(crate) key: u64,
+    /// Returns the element of the given handle parts, which are for its type.
+    get: fn(&Purse, usize, u64) -> Option<&dyn Any>,
+    /// Removes the element of the given handle parts.
+    remove: fn(&mut Purse, usize, u64) -> Option<Box<dyn Any>>,
+}
+
+impl ErasedHandle {
+    pub(crate) fn new<T: Any>(handle: ItemHandle<T>) -> Self {
+        Self {
+            index: handle.index,
+            key: handle.key,
+            get: get_any::<T>,
+            remove: remove_any::<T>,
+        }
+    }
+    /// Returns the element the handle refers to.
+    pub(crate) fn get<'a>(&self, purse: &'a Purse) -> Option<&'a dyn Any> {
+        (self.get)(purse, self.index, self.key)
+    }
+    /// Removes the element the handle refers to.
+    pub(crate) fn remove(&self, purse: &mut Purse) -> Option<Box<dyn Any>> {
+        (self.remove)(purse, self.index, self.key)
+    }
+}
+
+fn get_any<T: Any>(purse: &Purse, index: usize, key: u64) -> Option<&dyn Any> {
+    Some(purse.get(ItemHandle::<T>::new(index, key))?)
+}
+
+fn remove_any<T: Any>(purse: &mut Purse, index: usize, key: u64) -> Option<Box<dyn Any>> {
+    let elem = purse.remove_by_handle(ItemHandle::<T>::new(index, key))?;
+    Some(Box::new(elem))
+}
+
+impl<S: BuildHasher> Purse<S> {
+    /// Returns the keyed column of `T`, switching the type over to keyed storage if
+    /// it isn't registered otherwise.
+    fn keyed_column<T: Any>(&mut self) -> &mut KeyedColumn<T> {
+        let is_keyed = |purse: &mut Purse<S>| {
+            purse
+                .column_or_default::<T>()
+                .as_any_mut()
+                .is_some_and(|column| column.is::<KeyedColumn<T>>())
+        };
+        if !is_keyed(self) {
+            assert!(
+                !self.factories.contains_key(&TypeId::of::<T>()),
+                "`{}` is registered with a storage strategy that has no item handles",
+                core::any::type_name::<T>(),
+            );
+            self.register(Strategy::<T>::keyed());
+        }
+        self.column_or_default::<T>()
+            .as_any_mut()
+            .and_then(|column| column.downcast_mut())
+            .expect("the type was just registered as keyed")
+    }
+    /// Iterates over the elements in the order [`iter`](Purse::iter) has for a purse that
+    /// isn't insertion-ordered, skipping the elements whose handles have one of `keys`.
+    pub(crate) fn iter_without(
+        &self,
+        keys: HashSet<u64>,
+    ) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        let elems = self.data.values().flat_map(Bucket::iter_keyed);
+        Box::new(
+            elems
+                .filter(move |(key, _)| key.map_or(true, |key| !keys.contains(&key)))
+                .map(|(_, elem)| elem),
+        )
+    }
+    /// Inserts an element into the purse, returning a handle to that very element.
+    ///
+    /// Other methods treat equal elements as interchangeable, so [`remove`](Purse::remove)
+    /// may take out a different element than the one inserted. The handle reaches exactly
+    /// this element through [`get`](Purse::get), [`get_mut`](Purse::get_mut) and
+    /// [`remove_by_handle`](Purse::remove_by_handle), in constant time. Once the element is
+    /// removed by any means, or the purse is cleared, the handle no longer refers to anything.
+    ///
+    /// The first call for a type switches its storage to [`Strategy::keyed`], moving over
+    /// the elements already in the purse. Keyed storage otherwise behaves like a list, except
+    /// that iteration order follows the slots of the elements, which are reused after
+    /// removals. In an [insertion-ordered](Purse::insertion_ordered) purse the elements
+    /// still keep their place in the order of the purse.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element to insert. This type must implement `Any`.
+    ///
+    /// # Panics
+    /// Panics if `T` was registered with another strategy through
+    /// [`register`](Purse::register), for example with
+    /// [`register_hashed`](Purse::register_hashed), which keeps no individual elements to
+    /// point at.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// let first = purse.insert_with_handle("goblin");
+    /// let second = purse.insert_with_handle("goblin");
+    ///
+    /// assert_eq!(purse.remove_by_handle(second), Some("goblin"));
+    /// assert_eq!(purse.get(first), Some(&"goblin"));
+    /// assert_eq!(purse.get(second), None);
+    /// assert_eq!(purse.count::<&str>(), 1);
+    /// ```
+    pub fn insert_with_handle<T: Any>(&mut self, elem: T) -> ItemHandle<T> {
+        self.keyed_column::<T>().insert_keyed(elem)
+    }
+    /// Returns the element `handle` refers to.
+    ///
+    /// # Returns
+    /// `None` if the element was removed, or the handle belongs to another purse.
+    pub fn get<T: Any>(&self, handle: ItemHandle<T>) -> Option<&T> {
+        keyed(self.column::<T>()?)?.get(handle)
+    }
+    /// Returns a mutable reference to the element `handle` refers to.
+    ///
+    /// # Returns
+    /// `None` if the element was removed, or the handle belongs to another purse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// let hp = purse.insert_with_handle(10u32);
+    /// purse.insert(10u32);
+    /// *purse.get_mut(hp).unwrap() -= 3;
+    /// assert_eq!(purse.get_all_of_type::<u32>(), vec![&7, &10]);
+    /// ```
+    pub fn get_mut<T: Any>(&mut self, handle: ItemHandle<T>) -> Option<&mut T> {
+        let column = self.column_mut::<T>()?.as_any_mut()?;
+        column.downcast_mut::<KeyedColumn<T>>()?.get_mut(handle)
+    }
+    /// Removes the element `handle` refers to.
+    ///
+    /// # Returns
+    /// The removed element, or `None` if it was removed before or the handle belongs to
+    /// another purse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// let handle = purse.insert_with_handle(1u8);
+    /// purse.clear();
+    /// purse.insert_with_handle(1u8);
+    /// assert_eq!(purse.remove_by_handle(handle), None);
+    /// assert_eq!(purse.count::<u8>(), 1);
+    /// ```
+    pub fn remove_by_handle<T: Any>(&mut self, handle: ItemHandle<T>) -> Option<T> {
+        let column = self.column_mut::<T>()?.as_any_mut()?;
+        column.downcast_mut::<KeyedColumn<T>>()?.remove(handle)
+    }
+}
@@ -0,0 +1,174 @@
+//! Hash-indexed storage for types that opt into it via
+//! [`Purse::register_hashed`](crate::Purse::register_hashed).
+//!
+//! Instead of keeping every inserted item around, a [`HashedBag`] keeps each
+//! distinct value exactly once together with its multiplicity. Lookups and
+//! removals are then a single hash probe rather than a linear scan. Since
+//! equal values are collapsed, owned copies are handed back out by cloning the
+//! stored representative.
+
+use alloc::vec::Vec;
+use core::any::Any;
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+
+use crate::counted::{self, repeat_owned};
+use crate::map::{self, hash_map, DefaultState, HashMap};
+use crate::{Column, TypeIter};
+
+/// Iterator over the items of a [`HashedBag`].
+pub(crate) type Iter<'a, T> = counted::Iter<'a, T, hash_map::Iter<'a, T, u64>>;
+
+/// Stores each distinct value of `T` once, alongside the number of times it
+/// was inserted, hashing the values with `S`.
+#[derive(Clone)]
+pub(crate) struct HashedBag<T, S = DefaultState> {
+    counts: HashMap<T, u64, S>,
+    len: u64,
+}
+
+impl<T, S: Default> Default for HashedBag<T, S> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<T, S> HashedBag<T, S> {
+    /// Iterates over every stored item, yielding each distinct value as many
+    /// times as it was inserted.
+    pub(crate) fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self.counts.iter(), self.len)
+    }
+}
+
+impl<T: Hash + Eq, S: BuildHasher> HashedBag<T, S> {
+    pub(crate) fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.counts.contains_key(value)
+    }
+    pub(crate) fn multiplicity<Q>(&self, value: &Q) -> u64
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.counts.get(value).copied().unwrap_or(0)
+    }
+    pub(crate) fn remove_n<Q>(&mut self, value: &Q, n: u64) -> u64
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(count) = self.counts.get_mut(value) else {
+            return 0;
+        };
+        let removed = n.min(*count);
+        *count -= removed;
+        if *count == 0 {
+            self.counts.remove(value);
+        }
+        self.len -= removed;
+        removed
+    }
+}
+
+impl<T: Hash + Eq + Clone, S: BuildHasher + Default> HashedBag<T, S> {
+    pub(crate) fn insert_n(&mut self, value: T, n: u64) {
+        if n == 0 {
+            return;
+        }
+        *self.counts.entry(value).or_insert(0) += n;
+        self.len += n;
+    }
+    /// Removes and returns an arbitrary item.
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let value = self.counts.keys().next()?.clone();
+        self.remove_n(&value, 1);
+        Some(value)
+    }
+    /// Removes every item, yielding each value as many times as it was
+    /// inserted.
+    pub(crate) fn into_values(self) -> impl Iterator<Item = T> {
+        self.counts
+            .into_iter()
+            .flat_map(|(value, n)| repeat_owned(value, n))
+    }
+}
+
+impl<T, S> Column<T> for HashedBag<T, S>
+where
+    T: Any + Hash + Eq + Clone,
+    S: BuildHasher + Default + 'static,
+{
+    /// Saturates at `usize::MAX` on targets where it is below the `u64` count.
+    fn len(&self) -> usize {
+        usize::try_from(self.len).unwrap_or(usize::MAX)
+    }
+    fn insert(&mut self, value: T) {
+        self.insert_n(value, 1);
+    }
+    fn insert_n(&mut self, value: T, n: u64) {
+        HashedBag::insert_n(self, value, n);
+    }
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::hashed(self.iter())
+    }
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        self.counts.retain(|value, count| {
+            if removed < limit && matches(value) {
+                let n = (*count).min(limit - removed);
+                *count -= n;
+                removed += n;
+            }
+            *count > 0
+        });
+        self.len -= removed;
+        removed
+    }
+    /// Removes an arbitrary item.
+    fn pop(&mut self) -> Option<T> {
+        HashedBag::pop(self)
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        let len = usize::try_from(self.len).expect("the items don't fit in memory");
+        let mut elems = Vec::with_capacity(len);
+        elems.extend(core::mem::take(self).into_values());
+        elems
+    }
+    fn contains(&self, value: &T) -> bool {
+        HashedBag::contains(self, value)
+    }
+    fn count_of(&self, value: &T) -> u64 {
+        self.multiplicity(value)
+    }
+    fn remove_n(&mut self, value: &T, n: u64) -> u64 {
+        HashedBag::remove_n(self, value, n)
+    }
+    /// Returns how many distinct values the column can hold without allocating
+    /// again. Duplicates take no room.
+    fn capacity(&self) -> usize {
+        self.counts.capacity()
+    }
+    /// Makes room for at least `additional` more distinct values.
+    fn reserve(&mut self, additional: usize) {
+        self.counts.reserve(additional);
+    }
+    fn shrink_to_fit(&mut self) {
+        self.counts.shrink_to_fit();
+    }
+    fn heap_size(&self) -> usize {
+        map::table_size::<(T, u64)>(self.counts.capacity())
+    }
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
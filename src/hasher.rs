@@ -0,0 +1,48 @@
+//! A hasher for maps keyed by [`TypeId`](std::any::TypeId).
+//!
+//! A `TypeId` is already a hash of its type, so hashing it again with SipHash
+//! only costs time. [`TypeIdHasher`] passes the bits it is given through
+//! instead, which is what a [`Purse`](crate::Purse) uses for its per-type maps
+//! unless told otherwise with [`Purse::with_hasher`](crate::Purse::with_hasher).
+
+use core::hash::{BuildHasherDefault, Hasher};
+
+/// A hasher that returns the integers written to it, meant for keys that are
+/// already well distributed such as `TypeId`s.
+///
+/// Keys written as bytes are folded into the hash eight bytes at a time. It is
+/// a poor choice for anything else, including values chosen by an attacker.
+///
+/// # Examples
+///
+/// ```
+/// # use purse::TypeIdHasher;
+/// use std::hash::Hasher;
+/// let mut hasher = TypeIdHasher::default();
+/// hasher.write_u64(0xdead_beef);
+/// assert_eq!(hasher.finish(), 0xdead_beef);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TypeIdHasher(u64);
+
+impl Hasher for TypeIdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_u64(u64::from_ne_bytes(word));
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = self.0.rotate_left(5) ^ n;
+    }
+}
+
+/// Creates [`TypeIdHasher`]s. This is the default hasher of a
+/// [`Purse`](crate::Purse).
+pub type BuildTypeIdHasher = BuildHasherDefault<TypeIdHasher>;
@@ -0,0 +1,309 @@
+//! Iterators over the contents of a [`Purse`](crate::Purse).
+
+use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+use core::any::Any;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::slice;
+
+use crate::{hashed, keyed, sorted};
+
+/// A lazy iterator over all items of one type in a [`Purse`](crate::Purse).
+///
+/// Created by [`Purse::iter_of_type`](crate::Purse::iter_of_type). Unlike
+/// [`Purse::get_all_of_type`](crate::Purse::get_all_of_type), it does not
+/// allocate, except to box the iterator of a custom [`Column`](crate::Column),
+/// and it always knows exactly how many items are left.
+///
+/// As the boxed iterator may borrow the purse when it is dropped, the purse
+/// stays borrowed for as long as a `TypeIter` is alive, not just until its
+/// last use.
+#[derive(Clone, Debug)]
+pub struct TypeIter<'a, T> {
+    inner: Source<'a, T>,
+}
+
+/// Where the items of a [`TypeIter`] come from.
+#[derive(Clone, Debug)]
+enum Source<'a, T> {
+    /// A built-in column.
+    Builtin(Inner<'a, T>),
+    /// A custom column, see [`TypeIter::new`].
+    Column(Box<dyn ColumnIter<'a, T> + 'a>),
+}
+
+impl<'a, T> Iterator for Source<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Source::Builtin(iter) => iter.next(),
+            Source::Column(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Source::Builtin(iter) => iter.size_hint(),
+            Source::Column(iter) => iter.size_hint(),
+        }
+    }
+}
+
+/// The iterators of the built-in columns, behind [`TypeIter`] and
+/// [`RangeIter`].
+///
+/// None of the variants own anything that borrows from the purse in their
+/// `Drop`, so a live iterator only holds the purse borrowed until its last
+/// use.
+#[derive(Clone, Debug)]
+enum Inner<'a, T> {
+    Slice(slice::Iter<'a, T>),
+    Hashed(hashed::Iter<'a, T>),
+    Keyed(keyed::Iter<'a, T>),
+    Sorted(sorted::Iter<'a, T>),
+    SortedRange(sorted::Range<'a, T>),
+    Collected(vec::IntoIter<&'a T>),
+}
+
+/// The iterator of a custom [`Column`](crate::Column), behind
+/// [`TypeIter::new`].
+trait ColumnIter<'a, T: 'a>: ExactSizeIterator<Item = &'a T> {
+    fn clone_box(&self) -> Box<dyn ColumnIter<'a, T> + 'a>;
+}
+
+impl<'a, T: 'a, I> ColumnIter<'a, T> for I
+where
+    I: ExactSizeIterator<Item = &'a T> + Clone + 'a,
+{
+    fn clone_box(&self) -> Box<dyn ColumnIter<'a, T> + 'a> {
+        Box::new(self.clone())
+    }
+}
+
+impl<'a, T> Clone for Box<dyn ColumnIter<'a, T> + 'a> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl<T> fmt::Debug for dyn ColumnIter<'_, T> + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColumnIter")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, T> Iterator for Inner<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Inner::Slice(iter) => iter.next(),
+            Inner::Hashed(iter) => iter.next(),
+            Inner::Keyed(iter) => iter.next(),
+            Inner::Sorted(iter) => iter.next(),
+            Inner::SortedRange(iter) => iter.next(),
+            Inner::Collected(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Inner::Slice(iter) => iter.size_hint(),
+            Inner::Hashed(iter) => iter.size_hint(),
+            Inner::Keyed(iter) => iter.size_hint(),
+            Inner::Sorted(iter) => iter.size_hint(),
+            Inner::SortedRange(iter) => iter.size_hint(),
+            Inner::Collected(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<'a, T> TypeIter<'a, T> {
+    /// Creates an iterator for a custom [`Column`](crate::Column) from the
+    /// items it yields, each as many times as it was inserted.
+    ///
+    /// The iterator is boxed and advanced lazily, so like `TypeIter` itself
+    /// it has to know how many items are left and be cloneable. Columns that
+    /// keep their items in a slice can use [`from_slice`](TypeIter::from_slice)
+    /// instead, which doesn't allocate.
+    pub fn new<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = &'a T>,
+        I::IntoIter: ExactSizeIterator + Clone + 'a,
+    {
+        Self {
+            inner: Source::Column(Box::new(iter.into_iter())),
+        }
+    }
+
+    /// Creates an iterator over a slice of items.
+    pub fn from_slice(elems: &'a [T]) -> Self {
+        Self {
+            inner: Source::Builtin(Inner::Slice(elems.iter())),
+        }
+    }
+
+    pub(crate) fn hashed(iter: hashed::Iter<'a, T>) -> Self {
+        Self {
+            inner: Source::Builtin(Inner::Hashed(iter)),
+        }
+    }
+
+    pub(crate) fn keyed(iter: keyed::Iter<'a, T>) -> Self {
+        Self {
+            inner: Source::Builtin(Inner::Keyed(iter)),
+        }
+    }
+
+    pub(crate) fn sorted(iter: sorted::Iter<'a, T>) -> Self {
+        Self {
+            inner: Source::Builtin(Inner::Sorted(iter)),
+        }
+    }
+}
+
+impl<'a, T> Iterator for TypeIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for TypeIter<'_, T> {}
+
+impl<T> FusedIterator for TypeIter<'_, T> {}
+
+/// An iterator over the items of one type in a [`Purse`](crate::Purse) that
+/// fall within a range, in ascending order.
+///
+/// Created by [`Purse::range`](crate::Purse::range).
+#[derive(Clone, Debug)]
+pub struct RangeIter<'a, T> {
+    inner: Inner<'a, T>,
+}
+
+impl<'a, T> RangeIter<'a, T> {
+    /// Creates an iterator for a custom [`Column`](crate::Column) from items
+    /// that were already filtered and sorted.
+    pub fn from_sorted(elems: Vec<&'a T>) -> Self {
+        Self {
+            inner: Inner::Collected(elems.into_iter()),
+        }
+    }
+
+    pub(crate) fn sorted(iter: sorted::Range<'a, T>) -> Self {
+        Self {
+            inner: Inner::SortedRange(iter),
+        }
+    }
+}
+
+impl<'a, T> Iterator for RangeIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for RangeIter<'_, T> {}
+
+impl<T> FusedIterator for RangeIter<'_, T> {}
+
+/// A lazy iterator over mutable references to all items of one type in a
+/// [`Purse`](crate::Purse).
+///
+/// Created by [`Purse::iter_mut_of_type`](crate::Purse::iter_mut_of_type).
+#[derive(Debug)]
+pub struct TypeIterMut<'a, T> {
+    inner: InnerMut<'a, T>,
+}
+
+#[derive(Debug)]
+enum InnerMut<'a, T> {
+    Slice(slice::IterMut<'a, T>),
+    Keyed(keyed::IterMut<'a, T>),
+}
+
+impl<'a, T> TypeIterMut<'a, T> {
+    /// Creates an iterator over a mutable slice of items.
+    pub fn from_slice(elems: &'a mut [T]) -> Self {
+        Self {
+            inner: InnerMut::Slice(elems.iter_mut()),
+        }
+    }
+
+    pub(crate) fn keyed(iter: keyed::IterMut<'a, T>) -> Self {
+        Self {
+            inner: InnerMut::Keyed(iter),
+        }
+    }
+}
+
+impl<'a, T> Iterator for TypeIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            InnerMut::Slice(iter) => iter.next(),
+            InnerMut::Keyed(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            InnerMut::Slice(iter) => iter.size_hint(),
+            InnerMut::Keyed(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for TypeIterMut<'_, T> {}
+
+impl<T> FusedIterator for TypeIterMut<'_, T> {}
+
+/// An owning iterator over all items of a [`Purse`](crate::Purse), each boxed
+/// as `dyn Any`.
+///
+/// Created by the [`IntoIterator`] implementation of
+/// [`Purse`](crate::Purse).
+pub struct IntoIter {
+    inner: Box<dyn Iterator<Item = Box<dyn Any>>>,
+}
+
+impl IntoIter {
+    pub(crate) fn new(inner: Box<dyn Iterator<Item = Box<dyn Any>>>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = Box<dyn Any>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl fmt::Debug for IntoIter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntoIter").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,287 @@
+//! Slot storage for types whose items are referred to by
+//! [`ItemHandle`]s, see [`Purse::insert_with_handle`](crate::Purse::insert_with_handle).
+//!
+//! A [`KeyedColumn`] keeps its items in a vector of slots and recycles the
+//! slots of removed items. Every item gets a key from a process-wide counter
+//! when it is stored, and a handle remembers both the slot and the key. A
+//! handle whose slot has since been emptied or reused no longer matches, and
+//! since keys are never handed out twice, neither does a handle to an item of
+//! a column that was cleared, replaced or belongs to another purse.
+//!
+//! In an insertion-ordered purse the column also records the sequence number
+//! of the item in each slot, see [`SequencedColumn`](crate::sequenced::SequencedColumn).
+
+use alloc::vec::Vec;
+use core::any::Any;
+use core::iter::FusedIterator;
+use core::mem;
+use core::slice;
+
+use crate::counter::Counter;
+use crate::sequenced::NEXT_SEQUENCE;
+use crate::{Column, ItemHandle, TypeIter, TypeIterMut};
+
+static NEXT_KEY: Counter = Counter::new();
+
+#[derive(Clone, Debug)]
+struct Slot<T> {
+    key: u64,
+    value: Option<T>,
+}
+
+/// Stores items in slots that [`ItemHandle`]s point at.
+pub(crate) struct KeyedColumn<T> {
+    slots: Vec<Slot<T>>,
+    /// Indices of the empty slots, reused before the vector grows.
+    free: Vec<usize>,
+    len: usize,
+    /// The sequence number of each slot's item, for a column of an
+    /// insertion-ordered purse.
+    sequence: Option<Vec<u64>>,
+}
+
+impl<T> Default for KeyedColumn<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            sequence: None,
+        }
+    }
+}
+
+impl<T> KeyedColumn<T> {
+    /// Creates a column that records the sequence number of each item.
+    pub(crate) fn sequenced() -> Self {
+        Self {
+            sequence: Some(Vec::new()),
+            ..Self::default()
+        }
+    }
+    pub(crate) fn insert_keyed(&mut self, value: T) -> ItemHandle<T> {
+        let sequence = match self.sequence {
+            Some(_) => NEXT_SEQUENCE.next(),
+            None => 0,
+        };
+        self.insert_sequenced(value, sequence)
+    }
+    /// Stores an item, recording `sequence` as its sequence number if the column
+    /// records them.
+    pub(crate) fn insert_sequenced(&mut self, value: T, sequence: u64) -> ItemHandle<T> {
+        let key = NEXT_KEY.next();
+        let slot = Slot {
+            key,
+            value: Some(value),
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = slot;
+                index
+            }
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        };
+        self.len += 1;
+        if let Some(numbers) = &mut self.sequence {
+            match numbers.get_mut(index) {
+                Some(number) => *number = sequence,
+                None => numbers.push(sequence),
+            }
+        }
+        ItemHandle::new(index, key)
+    }
+    /// Iterates over the items along with the keys of their handles.
+    pub(crate) fn iter_keyed(&self) -> impl Iterator<Item = (u64, &T)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| Some((slot.key, slot.value.as_ref()?)))
+    }
+    /// Returns the sequence numbers of the items in the order of their slots, if the
+    /// column records them.
+    pub(crate) fn sequence(&self) -> Option<Vec<u64>> {
+        Some(
+            self.numbered(self.sequence.as_ref()?)
+                .map(|(n, _)| n)
+                .collect(),
+        )
+    }
+    /// Returns the item with the lowest sequence number and that number.
+    pub(crate) fn oldest(&self) -> Option<(u64, &T)> {
+        self.numbered(self.sequence.as_ref()?)
+            .min_by_key(|&(n, _)| n)
+    }
+    /// Returns the item with the highest sequence number and that number.
+    pub(crate) fn newest(&self) -> Option<(u64, &T)> {
+        self.numbered(self.sequence.as_ref()?)
+            .max_by_key(|&(n, _)| n)
+    }
+    fn numbered<'a>(&'a self, numbers: &'a [u64]) -> impl Iterator<Item = (u64, &'a T)> {
+        numbers
+            .iter()
+            .zip(&self.slots)
+            .filter_map(|(&n, slot)| Some((n, slot.value.as_ref()?)))
+    }
+    fn slot(&self, handle: ItemHandle<T>) -> Option<&Slot<T>> {
+        self.slots
+            .get(handle.index)
+            .filter(|slot| slot.key == handle.key)
+    }
+    pub(crate) fn get(&self, handle: ItemHandle<T>) -> Option<&T> {
+        self.slot(handle)?.value.as_ref()
+    }
+    pub(crate) fn get_mut(&mut self, handle: ItemHandle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.key == handle.key)?
+            .value
+            .as_mut()
+    }
+    pub(crate) fn remove(&mut self, handle: ItemHandle<T>) -> Option<T> {
+        self.slot(handle)?;
+        self.remove_at(handle.index)
+    }
+    fn remove_at(&mut self, index: usize) -> Option<T> {
+        let value = self.slots[index].value.take()?;
+        self.free.push(index);
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl<T: Any> Column<T> for KeyedColumn<T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn insert(&mut self, value: T) {
+        self.insert_keyed(value);
+    }
+    /// Iterates over the items in the order of their slots, which is the order of
+    /// insertion until items are removed.
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::keyed(Iter {
+            slots: self.slots.iter(),
+            remaining: self.len,
+        })
+    }
+    fn iter_mut(&mut self) -> Option<TypeIterMut<'_, T>> {
+        Some(TypeIterMut::keyed(IterMut {
+            slots: self.slots.iter_mut(),
+            remaining: self.len,
+        }))
+    }
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        for index in 0..self.slots.len() {
+            if removed == limit {
+                break;
+            }
+            if self.slots[index].value.as_ref().is_some_and(&mut *matches) {
+                self.remove_at(index);
+                removed += 1;
+            }
+        }
+        removed
+    }
+    /// Removes the item in the last occupied slot.
+    fn pop(&mut self) -> Option<T> {
+        let index = self.slots.iter().rposition(|slot| slot.value.is_some())?;
+        self.remove_at(index)
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        let sequence = self.sequence.as_ref().map(|_| Vec::new());
+        let column = mem::replace(
+            self,
+            Self {
+                sequence,
+                ..Self::default()
+            },
+        );
+        column
+            .slots
+            .into_iter()
+            .filter_map(|slot| slot.value)
+            .collect()
+    }
+    /// Counts the empty slots, which new items reuse, as well as the room set
+    /// aside for new slots.
+    fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+    fn reserve(&mut self, additional: usize) {
+        let additional = additional.saturating_sub(self.free.len());
+        self.slots.reserve(additional);
+        if let Some(numbers) = &mut self.sequence {
+            numbers.reserve(additional);
+        }
+    }
+    /// Frees the room set aside for new slots, but keeps the empty slots
+    /// themselves, since handles point at slots by their index.
+    fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit();
+        self.free.shrink_to_fit();
+        if let Some(numbers) = &mut self.sequence {
+            numbers.shrink_to_fit();
+        }
+    }
+    fn heap_size(&self) -> usize {
+        let numbers = self.sequence.as_ref().map_or(0, Vec::capacity);
+        self.slots.capacity() * mem::size_of::<Slot<T>>()
+            + self.free.capacity() * mem::size_of::<usize>()
+            + numbers * mem::size_of::<u64>()
+    }
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+/// Iterator over the items of a [`KeyedColumn`].
+#[derive(Clone, Debug)]
+pub(crate) struct Iter<'a, T> {
+    slots: slice::Iter<'a, Slot<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.slots.find_map(|slot| slot.value.as_ref())?;
+        self.remaining -= 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// Iterator over mutable references to the items of a [`KeyedColumn`].
+#[derive(Debug)]
+pub(crate) struct IterMut<'a, T> {
+    slots: slice::IterMut<'a, Slot<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.slots.find_map(|slot| slot.value.as_mut())?;
+        self.remaining -= 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
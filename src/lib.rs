@@ -52,26 +52,427 @@
 //! assert_eq!(nums.first(), Some(&5));
 //! assert_eq!(strs.first(), Some(&"foo"));
 //! ```
+//!
+//! # Features
+//!
+//! The `std` feature is enabled by default. Without it the crate only depends
+//! on `core` and `alloc` and can be used in `no_std` programs, minus the types
+//! that block on std's locks: [`ConcurrentPurse`] and [`Mailbox`], and
+//! `SystemClock`, which reads std's clock. Purses that measure time take any
+//! [`Clock`] instead.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
-use std::any::{Any, TypeId};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
 
-use std::collections::HashMap;
+mod algebra;
+mod bounded;
+mod clock;
+mod column;
+mod counted;
+mod counter;
+mod error;
+mod expiring;
+mod handle;
+mod hashed;
+mod hasher;
+mod iter;
+mod keyed;
+mod map;
+mod memory;
+mod order;
+mod quota;
+mod sequenced;
+mod sliding;
+mod sorted;
+mod sync;
+mod vtable;
 
-trait ClonableAny: Any + Clone + Sized {}
+pub use bounded::{BoundedPurse, Eviction};
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use clock::{Clock, ManualClock};
+pub use column::{Column, Strategy};
+pub use error::PurseError;
+pub use expiring::ExpiringPurse;
+pub use handle::ItemHandle;
+pub use hasher::{BuildTypeIdHasher, TypeIdHasher};
+pub use iter::{IntoIter, RangeIter, TypeIter, TypeIterMut};
+pub use memory::{MemoryUsage, TypeMemory};
+pub use quota::{Limit, Quota};
+pub use sliding::{SlidingPurse, Window};
+pub use sync::SendPurse;
+#[cfg(feature = "std")]
+pub use sync::{ConcurrentPurse, Mailbox, WaitFor};
+pub use vtable::ErasedValue;
+
+use column::{AnyColumn, ListColumn};
+use hashed::HashedBag;
+use map::HashMap;
+use sequenced::{insert_sequenced, SequencedColumn};
+use vtable::VTable;
+
+/// Backing storage for all items of a single type: a `Box<dyn Column<T>>`,
+/// erased so that buckets of different types fit in one map.
+#[derive(Debug)]
+struct Bucket(Box<dyn AnyColumn>);
+
+impl Bucket {
+    fn new<T: Any>(column: Box<dyn Column<T>>) -> Self {
+        Bucket(Box::new(column))
+    }
+    /// Creates an empty list bucket, which types use unless registered otherwise.
+    fn list<T: Any>() -> Self {
+        Bucket::new(Box::<ListColumn<T>>::default())
+    }
+    /// Creates an empty list bucket that records the order of insertion across types, which
+    /// insertion-ordered purses use instead of [`list`](Bucket::list).
+    fn sequenced<T: Any>() -> Self {
+        Bucket::new(Box::<SequencedColumn<T>>::default())
+    }
+    fn column<T: Any>(&self) -> Option<&dyn Column<T>> {
+        let column = self.0.as_any().downcast_ref::<Box<dyn Column<T>>>()?;
+        Some(&**column)
+    }
+    fn column_mut<T: Any>(&mut self) -> Option<&mut dyn Column<T>> {
+        let column = self.0.as_any_mut().downcast_mut::<Box<dyn Column<T>>>()?;
+        Some(&mut **column)
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Returns the bytes taken up by the items themselves, not counting the memory
+    /// they own.
+    fn len_bytes(&self) -> usize {
+        self.len() * self.0.value_size()
+    }
+    fn type_name(&self) -> &'static str {
+        self.0.type_name()
+    }
+    fn iter_of<T: Any>(&self) -> TypeIter<'_, T> {
+        self.column::<T>()
+            .map_or(TypeIter::from_slice(&[]), |column| column.iter())
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        self.0.iter_any()
+    }
+    /// Iterates over the items along with the keys of their handles, which only items of
+    /// a keyed column have.
+    fn iter_keyed(&self) -> Box<dyn Iterator<Item = (Option<u64>, &dyn Any)> + '_> {
+        match self.0.iter_keyed_any() {
+            Some(elems) => Box::new(elems.map(|(key, elem)| (Some(key), elem))),
+            None => Box::new(self.iter().map(|elem| (None, elem))),
+        }
+    }
+    fn last(&self) -> Option<&dyn Any> {
+        let index = self.len().checked_sub(1)?;
+        self.0.get_any(index).or_else(|| self.iter().last())
+    }
+    /// Returns the sequence number of each item, in the order of
+    /// [`iter`](Bucket::iter), if the bucket belongs to an insertion-ordered purse and
+    /// keeps individual items.
+    fn sequence(&self) -> Option<Cow<'_, [u64]>> {
+        self.0.sequence()
+    }
+    /// Returns the item that was inserted first, if the bucket has sequence numbers.
+    fn oldest(&self) -> Option<(u64, &dyn Any)> {
+        self.0.oldest()
+    }
+    /// Returns the item that was inserted last, if the bucket has sequence numbers.
+    fn newest(&self) -> Option<(u64, &dyn Any)> {
+        self.0.newest()
+    }
+    /// Iterates over the items of columns that can be changed in place. Changing an item of
+    /// a hashed or ordered column would invalidate its hash or its position in the order.
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut dyn Any> + '_> {
+        self.0.iter_mut_any()
+    }
+    fn into_boxed_iter(self) -> Box<dyn Iterator<Item = Box<dyn Any>>> {
+        self.0.into_boxed_iter()
+    }
+}
 
-#[derive(Default, Debug)]
-pub struct Purse {
-    data: HashMap<TypeId, Vec<Box<dyn Any>>>,
-    counts: HashMap<TypeId, u64>,
+/// A bag of elements of any type.
+///
+/// The type parameter `S` hashes the `TypeId`s the purse keeps its types under. It
+/// defaults to [`BuildTypeIdHasher`], which doesn't hash them again; see
+/// [`with_hasher`](Purse::with_hasher) to pick another.
+///
+/// # Storage
+///
+/// Elements are stored by value, with the elements of each type in a column of their own,
+/// a `Vec<T>` unless the type is [registered](Purse::register) with another [`Strategy`].
+/// Inserting a `u32` or a small enum writes it into its column rather than boxing it, so
+/// the only allocations are the columns growing. Elements of a zero-sized type take up no
+/// space in a list, and a [hashed](Strategy::hashed) column keeps their single value once,
+/// which leaves the purse with just their count. Other columns still spend memory per
+/// element: [keyed](Strategy::keyed) storage a key, and the lists of an
+/// [insertion-ordered](Purse::insertion_ordered) purse a sequence number.
+///
+/// ```
+/// # use purse::Purse;
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Tick;
+///
+/// let mut purse = Purse::new();
+/// purse.insert(1u8);
+/// purse.insert(2u8);
+/// purse.insert_n(Tick, 1_000);
+/// // Values sit next to each other in their column.
+/// assert_eq!(purse.as_slice::<u8>(), Some(&[1, 2][..]));
+/// assert_eq!(purse.count::<Tick>(), 1_000);
+/// ```
+#[derive(Default)]
+pub struct Purse<S = BuildTypeIdHasher> {
+    /// Items of each type. A bucket may be empty once its items are removed, so the
+    /// bucket lengths are the only record of how many items the purse holds.
+    data: HashMap<TypeId, Bucket, S>,
+    factories: HashMap<TypeId, fn() -> Bucket, S>,
+    vtables: HashMap<TypeId, VTable, S>,
+    /// Whether types that aren't registered record the order of insertion across types.
+    insertion_ordered: bool,
+    quota: Quota,
 }
 
 impl Purse {
     pub fn new() -> Self {
+        Self::with_hasher(BuildTypeIdHasher::default())
+    }
+    /// Creates an empty purse with room for at least `capacity` types before it
+    /// reallocates its maps.
+    ///
+    /// Use [`reserve`](Purse::reserve) to make room for the elements of a type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let purse = Purse::with_capacity(8);
+    /// assert!(purse.is_empty());
+    /// assert!(purse.memory_usage().maps > 0);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, BuildTypeIdHasher::default())
+    }
+    /// Creates a purse that remembers the order in which elements were inserted across types.
+    ///
+    /// A plain purse keeps its types in a `HashMap`, so [`iter`](Purse::iter) and
+    /// [`types`](Purse::types) visit the types in an order that changes from run to run. An
+    /// insertion-ordered purse instead yields elements in the order they were inserted,
+    /// interleaving types, and lists types by their oldest element. [`first`](Purse::first)
+    /// and [`last`](Purse::last) then return the oldest and newest elements, and the `Debug`
+    /// output is stable as well.
+    ///
+    /// This costs a sequence number per element, and sorting those when iterating over the
+    /// whole purse. Types registered with [`Strategy::list`] or [`Strategy::keyed`], which
+    /// includes types inserted with [`insert_with_handle`](Purse::insert_with_handle), keep
+    /// their elements' place in the order. Elements of types registered with other
+    /// strategies keep to the order of their storage instead, and come after all other
+    /// elements, sorted by type name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// use std::any::TypeId;
+    /// let mut purse = Purse::insertion_ordered();
+    /// purse.insert_value('a');
+    /// purse.insert_value(1u8);
+    /// purse.insert_value('b');
+    /// purse.insert_value("c");
+    ///
+    /// let types = [TypeId::of::<char>(), TypeId::of::<u8>(), TypeId::of::<&str>()];
+    /// assert_eq!(purse.types(), types);
+    /// assert_eq!(purse.first().and_then(|e| e.downcast_ref()), Some(&'a'));
+    /// assert_eq!(purse.last().and_then(|e| e.downcast_ref()), Some(&"c"));
+    /// assert_eq!(format!("{purse:?}"), r#"{"char": ['a', 'b'], "u8": [1], "&str": ["c"]}"#);
+    ///
+    /// purse.remove('a');
+    /// assert_eq!(purse.first().and_then(|e| e.downcast_ref()), Some(&1u8));
+    /// ```
+    pub fn insertion_ordered() -> Self {
+        Self {
+            insertion_ordered: true,
+            ..Self::new()
+        }
+    }
+}
+
+impl<S: BuildHasher + Clone> Purse<S> {
+    /// Creates an empty purse that hashes the `TypeId`s of its types with `hasher`.
+    ///
+    /// `TypeId`s are already hashes, so the default [`BuildTypeIdHasher`] is the fastest
+    /// choice. This is meant for callers that need a specific hasher throughout, for
+    /// example std's `RandomState`. The hasher for the values of a type stored with
+    /// [`Strategy::hashed`] is chosen separately, with [`Strategy::hashed_with_hasher`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// use std::collections::hash_map::RandomState;
+    /// let mut purse = Purse::with_hasher(RandomState::new());
+    /// purse.insert(42);
+    /// assert!(purse.contains(42));
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_capacity_and_hasher(0, hasher)
+    }
+    /// Creates an empty purse with room for at least `capacity` types, hashing their
+    /// `TypeId`s with `hasher`. See [`with_capacity`](Purse::with_capacity).
+    ///
+    /// Only the map holding the elements of each type is sized up front, as types
+    /// are rarely all registered or all inserted with
+    /// [`insert_value`](Purse::insert_value).
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
         Self {
-            data: HashMap::default(),
-            counts: HashMap::default(),
+            data: HashMap::with_capacity_and_hasher(capacity, hasher.clone()),
+            factories: HashMap::with_hasher(hasher.clone()),
+            vtables: HashMap::with_hasher(hasher),
+            insertion_ordered: false,
+            quota: Quota::default(),
+        }
+    }
+}
+
+impl<S: BuildHasher> Purse<S> {
+    /// Checks if the purse was created with [`insertion_ordered`](Purse::insertion_ordered).
+    pub fn is_insertion_ordered(&self) -> bool {
+        self.insertion_ordered
+    }
+    /// Chooses how the elements of type `T` are stored.
+    ///
+    /// Every method that works on elements of `T` dispatches to the [`Column`] created by
+    /// `strategy`, so the choice decides how fast lookups and removals are, the order in which
+    /// elements are iterated, and whether they can be changed in place. See [`Strategy`] for
+    /// the built-in choices, or implement [`Column`] to plug in another backend.
+    ///
+    /// Items of type `T` already in the purse are moved over, and the registration survives
+    /// calls to [`clear`](Purse::clear). Registering a type replaces any earlier registration
+    /// of it.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type whose storage to choose.
+    ///
+    /// # Arguments
+    /// - `strategy`: The storage to use for `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::{Purse, Strategy};
+    /// let mut purse = Purse::new();
+    /// purse.insert(3u8);
+    /// purse.insert(1u8);
+    /// purse.register::<u8>(Strategy::ordered());
+    /// assert_eq!(purse.get_all_of_type::<u8>(), vec![&1, &3]);
+    ///
+    /// purse.register::<u8>(Strategy::list());
+    /// purse.insert(2u8);
+    /// assert_eq!(purse.as_slice::<u8>(), Some(&[1u8, 3, 2][..]));
+    /// ```
+    pub fn register<T: Any>(&mut self, strategy: Strategy<T>) {
+        let type_id = TypeId::of::<T>();
+        let factory = strategy.factory(self.insertion_ordered);
+        self.factories.insert(type_id, factory);
+        if let Some(bucket) = self.data.get(&type_id) {
+            let sequence = bucket.sequence().map(Cow::into_owned);
+            let elems = self.take_all_of_type::<T>();
+            self.data.insert(type_id, factory());
+            // The elements were in the purse already, so they don't count against its quota
+            // again.
+            let column = self.column_or_default::<T>();
+            match sequence {
+                Some(sequence) => {
+                    insert_sequenced(column, sequence.into_iter().zip(elems).collect())
+                }
+                None => elems.into_iter().for_each(|elem| column.insert(elem)),
+            }
         }
     }
+    /// Switches the storage for type `T` to a hash-indexed bag.
+    ///
+    /// By default a purse keeps every inserted item in a list, so `contains` and `remove`
+    /// have to scan all items of the type. Once registered, each distinct value of `T` is
+    /// stored once along with its multiplicity, making lookups and removals constant time.
+    /// Items of type `T` already in the purse are moved over, and the registration survives
+    /// calls to [`clear`](Purse::clear).
+    ///
+    /// Iteration over a hashed type yields equal values next to each other, in no particular
+    /// order. Since equal values are only stored once, owned copies handed out by methods
+    /// such as [`take_all_of_type`](Purse::take_all_of_type) are clones of that value.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type to store hashed. This type must implement `Any`, `Hash`, `Eq` and
+    ///   `Clone`.
+    ///
+    /// Registering a type replaces any earlier registration of it, such as
+    /// [`register_ordered`](Purse::register_ordered).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(7u64);
+    /// purse.register_hashed::<u64>();
+    /// for n in 0..10_000u64 {
+    ///     purse.insert(n);
+    /// }
+    /// assert!(purse.contains(7u64));
+    /// assert_eq!(purse.count::<u64>(), 10_001);
+    /// assert!(purse.remove(7u64));
+    /// assert!(purse.remove(7u64));
+    /// assert!(!purse.contains(7u64));
+    /// ```
+    pub fn register_hashed<T: Any + Hash + Eq + Clone>(&mut self) {
+        self.register(Strategy::<T>::hashed());
+    }
+    /// Switches the storage for type `T` to an ordered bag.
+    ///
+    /// Like [`register_hashed`](Purse::register_hashed), each distinct value of `T` is stored
+    /// once along with its multiplicity, but values are kept sorted. Lookups and removals take
+    /// logarithmic time, iteration yields items in ascending order, and [`min`](Purse::min)
+    /// and [`max`](Purse::max) take logarithmic time too. The other order statistics skip
+    /// sorting, but still walk the distinct values: [`range`](Purse::range) those in the
+    /// range, [`rank`](Purse::rank) those below `value` and
+    /// [`nth_smallest`](Purse::nth_smallest) those up to position `n`, so they take time
+    /// linear in the number of distinct values rather than the number of items. Methods such
+    /// as [`contains`](Purse::contains) and [`count_of`](Purse::count_of) behave exactly as
+    /// they do for list storage.
+    ///
+    /// Items of type `T` already in the purse are moved over, and the registration survives
+    /// calls to [`clear`](Purse::clear). Registering a type replaces any earlier registration
+    /// of it.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type to store ordered. This type must implement `Any`, `Ord` and `Clone`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(30u32);
+    /// purse.register_ordered::<u32>();
+    /// purse.insert(10u32);
+    /// purse.insert(20u32);
+    /// purse.insert(10u32);
+    /// assert_eq!(purse.get_all_of_type::<u32>(), vec![&10, &10, &20, &30]);
+    /// assert_eq!(purse.min::<u32>(), Some(&10));
+    /// assert_eq!(purse.count_of(&10u32), 2);
+    /// ```
+    pub fn register_ordered<T: Any + Ord + Clone>(&mut self) {
+        self.register(Strategy::<T>::ordered());
+    }
     /// Checks if the purse is empty.
     ///
     /// # Examples
@@ -84,12 +485,71 @@ impl Purse {
     /// assert!(!purse.is_empty());
     /// ```
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.non_empty_buckets().next().is_none()
+    }
+    /// Returns the total number of elements in the purse, across all types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert("apple");
+    /// purse.insert("apple");
+    /// purse.insert(42);
+    /// assert_eq!(purse.len(), 3);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.data
+            .values()
+            .map(Bucket::len)
+            .fold(0, usize::saturating_add)
+    }
+    /// Returns the number of distinct types with at least one element in the purse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert("apple");
+    /// purse.insert("pear");
+    /// purse.insert(42);
+    /// assert_eq!(purse.len_types(), 2);
+    /// purse.remove(42);
+    /// assert_eq!(purse.len_types(), 1);
+    /// ```
+    pub fn len_types(&self) -> usize {
+        self.non_empty_buckets().count()
+    }
+    /// Iterates over the buckets that hold at least one element.
+    fn non_empty_buckets(&self) -> impl Iterator<Item = (&TypeId, &Bucket)> {
+        self.data.iter().filter(|(_, bucket)| bucket.len() > 0)
+    }
+    /// Lists the buckets that hold at least one element, in the order of
+    /// [`iter`](Purse::iter).
+    ///
+    /// In an insertion-ordered purse that is by the sequence number of each bucket's oldest
+    /// element, with buckets of registered types last, by type name.
+    fn ordered_buckets(&self) -> Vec<(&TypeId, &Bucket)> {
+        let mut buckets: Vec<(&TypeId, &Bucket)> = self.non_empty_buckets().collect();
+        if self.insertion_ordered {
+            buckets.sort_by_key(|(_, bucket)| {
+                let oldest = bucket.oldest().map(|(sequence, _)| sequence);
+                (oldest.unwrap_or(u64::MAX), bucket.type_name())
+            });
+        }
+        buckets
+    }
+    /// Returns the number of elements stored under `type_id`.
+    fn len_of(&self, type_id: &TypeId) -> usize {
+        self.data.get(type_id).map_or(0, Bucket::len)
     }
     /// Provides an iterator over all elements in the purse.
     ///
     /// This method returns a boxed iterator that yields references to each element stored in the purse,
-    /// regardless of their type. It iterates over all elements in a non-specific order.
+    /// regardless of their type. It iterates over all elements in a non-specific order, unless
+    /// the purse is [insertion-ordered](Purse::insertion_ordered).
     ///
     /// # Returns
     /// A `Box<dyn Iterator<Item = &dyn Any> + '_>` that can be used to iterate over all elements
@@ -107,7 +567,102 @@ impl Purse {
     /// }
     /// ```
     pub fn iter(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
-        Box::new(self.data.values().flatten().map(|b| &**b))
+        if !self.insertion_ordered {
+            return Box::new(self.data.values().flat_map(Bucket::iter));
+        }
+        let mut sequenced: Vec<(u64, &dyn Any)> = Vec::with_capacity(self.len());
+        let mut registered = Vec::new();
+        for (_, bucket) in self.ordered_buckets() {
+            match bucket.sequence() {
+                Some(sequence) => sequenced.extend(sequence.iter().copied().zip(bucket.iter())),
+                None => registered.push(bucket),
+            }
+        }
+        sequenced.sort_unstable_by_key(|&(seq, _)| seq);
+        let registered = registered.into_iter().flat_map(Bucket::iter);
+        Box::new(
+            sequenced
+                .into_iter()
+                .map(|(_, elem)| elem)
+                .chain(registered),
+        )
+    }
+    /// Returns the element [`iter`](Purse::iter) yields first.
+    ///
+    /// In an [insertion-ordered](Purse::insertion_ordered) purse this is the oldest element,
+    /// otherwise an arbitrary one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::insertion_ordered();
+    /// assert!(purse.first().is_none());
+    /// purse.insert(1u8);
+    /// purse.insert("two");
+    /// assert_eq!(purse.first().and_then(|e| e.downcast_ref()), Some(&1u8));
+    /// ```
+    pub fn first(&self) -> Option<&dyn Any> {
+        let (_, bucket) = self.ordered_buckets().into_iter().next()?;
+        match bucket.oldest() {
+            Some((_, elem)) => Some(elem),
+            None => bucket.iter().next(),
+        }
+    }
+    /// Returns the element [`iter`](Purse::iter) yields last.
+    ///
+    /// In an [insertion-ordered](Purse::insertion_ordered) purse this is the newest element,
+    /// unless the purse holds elements of registered types, otherwise an arbitrary one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::insertion_ordered();
+    /// purse.insert(1u8);
+    /// purse.insert("two");
+    /// purse.insert(3u8);
+    /// assert_eq!(purse.last().and_then(|e| e.downcast_ref()), Some(&3u8));
+    /// purse.pop::<u8>();
+    /// assert_eq!(purse.last().and_then(|e| e.downcast_ref()), Some(&"two"));
+    /// ```
+    pub fn last(&self) -> Option<&dyn Any> {
+        let buckets = self.ordered_buckets();
+        match buckets.last() {
+            Some((_, bucket)) if !self.insertion_ordered || bucket.sequence().is_none() => {
+                bucket.last()
+            }
+            _ => {
+                let newest = buckets.iter().filter_map(|(_, bucket)| bucket.newest());
+                newest
+                    .max_by_key(|&(sequence, _)| sequence)
+                    .map(|(_, elem)| elem)
+            }
+        }
+    }
+    /// Provides an iterator over mutable references to all elements in the purse.
+    ///
+    /// Elements can be updated in place through `downcast_mut`, without changing their
+    /// position. Only elements stored in a list, the default [`Strategy`], are visited: changing
+    /// elements of hashed or ordered types would invalidate their hash or their place in the
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1);
+    /// purse.insert("hello");
+    /// for item in purse.iter_mut() {
+    ///     if let Some(n) = item.downcast_mut::<i32>() {
+    ///         *n += 1;
+    ///     }
+    /// }
+    /// assert!(purse.contains(2));
+    /// ```
+    pub fn iter_mut(&mut self) -> Box<dyn Iterator<Item = &mut dyn Any> + '_> {
+        Box::new(self.data.values_mut().flat_map(Bucket::iter_mut))
     }
     /// Retrieves all elements of a specific type from the purse.
     ///
@@ -130,13 +685,128 @@ impl Purse {
     /// assert_eq!(*numbers[1], 42);
     /// ```
     pub fn get_all_of_type<T: Any>(&self) -> Vec<&T> {
+        self.iter_of_type().collect()
+    }
+    /// Retrieves mutable references to all elements of a specific type from the purse.
+    ///
+    /// Elements keep their position, so this can be used to update items without removing and
+    /// re-inserting them. Returns an empty vector unless `T` is stored in a list, the default
+    /// [`Strategy`], or in slots for [item handles](Purse::insert_with_handle).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// struct Monster {
+    ///     hit_points: u32,
+    /// }
+    /// let mut purse = Purse::new();
+    /// purse.insert(Monster { hit_points: 10 });
+    /// purse.insert(Monster { hit_points: 20 });
+    /// for monster in purse.get_all_of_type_mut::<Monster>() {
+    ///     monster.hit_points += 5;
+    /// }
+    /// let hit_points: Vec<u32> = purse.iter_of_type::<Monster>().map(|m| m.hit_points).collect();
+    /// assert_eq!(hit_points, vec![15, 25]);
+    /// ```
+    pub fn get_all_of_type_mut<T: Any>(&mut self) -> Vec<&mut T> {
+        self.iter_mut_of_type().collect()
+    }
+    /// Provides a lazy iterator over all elements of a specific type.
+    ///
+    /// This is the non-allocating counterpart of [`get_all_of_type`](Purse::get_all_of_type).
+    /// The returned iterator reports its exact length through `size_hint` and `len`.
+    ///
+    /// # Returns
+    /// A [`TypeIter`] yielding references to all elements of type `T` in the purse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1);
+    /// purse.insert(2);
+    /// purse.insert("three");
+    /// let numbers = purse.iter_of_type::<i32>();
+    /// assert_eq!(numbers.len(), 2);
+    /// assert_eq!(numbers.sum::<i32>(), 3);
+    /// ```
+    pub fn iter_of_type<T: Any>(&self) -> TypeIter<'_, T> {
         let type_id = TypeId::of::<T>();
-        self.data.get(&type_id).map_or(Vec::new(), |elems| {
-            elems
-                .iter()
-                .filter_map(|el| el.downcast_ref::<T>())
-                .collect()
-        })
+        self.data
+            .get(&type_id)
+            .map_or(TypeIter::from_slice(&[]), Bucket::iter_of)
+    }
+    /// Provides a lazy iterator over mutable references to all elements of a specific type.
+    ///
+    /// The iterator is empty unless `T` is stored in a list, the default [`Strategy`], or in
+    /// slots for [item handles](Purse::insert_with_handle).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1);
+    /// purse.insert(2);
+    /// purse.iter_mut_of_type::<i32>().for_each(|n| *n *= 10);
+    /// assert_eq!(purse.get_all_of_type::<i32>(), vec![&10, &20]);
+    /// ```
+    pub fn iter_mut_of_type<T: Any>(&mut self) -> TypeIterMut<'_, T> {
+        self.column_mut::<T>()
+            .and_then(Column::iter_mut)
+            .unwrap_or_else(|| TypeIterMut::from_slice(&mut []))
+    }
+    /// Returns all elements of a specific type as a contiguous slice, in insertion order.
+    ///
+    /// # Returns
+    /// The elements of type `T`, or an empty slice if there are none. Returns `None` if `T`
+    /// is stored in a [`Column`] that does not keep its items contiguous, such as the ones
+    /// used by [`register_hashed`](Purse::register_hashed) and
+    /// [`register_ordered`](Purse::register_ordered).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1u8);
+    /// purse.insert(2u8);
+    /// assert_eq!(purse.as_slice::<u8>(), Some(&[1u8, 2][..]));
+    /// assert_eq!(purse.as_slice::<u16>(), Some(&[][..]));
+    ///
+    /// purse.register_hashed::<u8>();
+    /// assert_eq!(purse.as_slice::<u8>(), None);
+    /// ```
+    pub fn as_slice<T: Any>(&self) -> Option<&[T]> {
+        match self.column::<T>() {
+            Some(column) => column.as_slice(),
+            None => Some(&[]),
+        }
+    }
+    /// Returns all elements of a specific type as a mutable slice, in insertion order.
+    ///
+    /// # Returns
+    /// The elements of type `T`, or an empty slice if there are none. Returns `None` if `T`
+    /// is stored in a [`Column`] that does not keep its items contiguous.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(3u8);
+    /// purse.insert(1u8);
+    /// purse.insert(2u8);
+    /// purse.as_mut_slice::<u8>().unwrap().sort();
+    /// assert_eq!(purse.as_slice::<u8>(), Some(&[1u8, 2, 3][..]));
+    /// ```
+    pub fn as_mut_slice<T: Any>(&mut self) -> Option<&mut [T]> {
+        match self.column_mut::<T>() {
+            Some(column) => column.as_mut_slice(),
+            None => Some(&mut []),
+        }
     }
     /// Checks if the purse contains a given element.
     ///
@@ -160,23 +830,52 @@ impl Purse {
     /// assert!(purse.contains("apple"));
     /// ```
     pub fn contains<T: Any + Eq>(&self, t: T) -> bool {
-        let type_id = TypeId::of::<T>();
-        let Some(elems) = self.data.get(&type_id) else {
+        self.column::<T>().is_some_and(|column| column.contains(&t))
+    }
+    /// Checks if the purse contains an element of type `T` that borrows as `value`.
+    ///
+    /// This mirrors [`HashSet::contains`](std::collections::HashSet::contains): the lookup
+    /// value only needs to be a borrowed form of the stored type, so checking for a `String`
+    /// does not require allocating one. Since many types can borrow as the same `Q`, the
+    /// stored type `T` has to be named explicitly.
+    ///
+    /// # Type Parameters
+    /// - `T`: The stored type to look in. This type must implement `Any`, `Hash`, `Eq` and
+    ///   `Borrow<Q>`.
+    /// - `Q`: The borrowed form used for the lookup, which must hash and compare the same way
+    ///   as `T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(String::from("abc"));
+    /// assert!(purse.contains_borrowed::<String, str>("abc"));
+    /// assert!(!purse.contains_borrowed::<String, str>("xyz"));
+    /// ```
+    pub fn contains_borrowed<T, Q>(&self, value: &Q) -> bool
+    where
+        T: Any + Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(column) = self.column::<T>() else {
             return false;
         };
-        for elem in elems {
-            if let Some(elem) = elem.downcast_ref::<T>() {
-                if elem == &t {
-                    return true;
-                }
-            }
+        match column
+            .as_any()
+            .and_then(|c| c.downcast_ref::<HashedBag<T>>())
+        {
+            Some(bag) => bag.contains(value),
+            None => column.iter().any(|el| el.borrow() == value),
         }
-        false
     }
     /// Retrieves a list of `TypeId`s of the types currently stored in the purse.
     ///
     /// This method returns a vector containing the `TypeId` of each unique type currently stored in the purse.
-    /// It iterates over a `HashMap` to collect the type identifiers.
+    /// It iterates over a `HashMap` to collect the type identifiers, skipping types whose elements have
+    /// all been removed. In an [insertion-ordered](Purse::insertion_ordered) purse, types are
+    /// listed in the order of their oldest element.
     ///
     /// # Returns
     /// A `Vec<TypeId>` containing the unique type identifiers of all elements stored in the purse.
@@ -193,12 +892,15 @@ impl Purse {
     /// assert!(types.contains(&TypeId::of::<&str>()));
     /// ```
     pub fn types(&self) -> Vec<TypeId> {
-        self.data.keys().cloned().collect()
+        self.ordered_buckets()
+            .into_iter()
+            .map(|(type_id, _)| *type_id)
+            .collect()
     }
     /// Counts the number of elements of a specific type in the purse.
     ///
     /// This method returns the number of elements of the type specified by the generic parameter `T`.
-    /// It accesses a `HashMap` that tracks counts of each type to retrieve the count.
+    /// It looks up the storage for that type, which tracks its own length.
     ///
     /// # Type Parameters
     /// - `T`: The type for which to count the occurrences. This type must implement the `Any` trait.
@@ -215,13 +917,37 @@ impl Purse {
     /// assert_eq!(purse.count::<i32>(), 2);
     /// ```
     pub fn count<T: Any>(&self) -> u64 {
-        let type_id = TypeId::of::<T>();
-        *self.counts.get(&type_id).unwrap_or(&0)
+        self.len_of(&TypeId::of::<T>()) as u64
+    }
+    /// Counts the occurrences of a specific element in the purse.
+    ///
+    /// Unlike [`count`](Purse::count), which counts all elements of a type, this method only
+    /// counts elements equal to `elem`. For types registered with
+    /// [`register_hashed`](Purse::register_hashed) or
+    /// [`register_ordered`](Purse::register_ordered) this is a single lookup.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element to count. This type must implement `Any` and `Eq`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(42);
+    /// purse.insert(42);
+    /// purse.insert(7);
+    /// assert_eq!(purse.count_of(&42), 2);
+    /// assert_eq!(purse.count_of(&0), 0);
+    /// ```
+    pub fn count_of<T: Any + Eq>(&self, elem: &T) -> u64 {
+        self.column::<T>().map_or(0, |column| column.count_of(elem))
     }
     /// Determines the most common type stored in the purse.
     ///
-    /// This method returns the `TypeId` of the most frequently occurring type.
-    /// It checks the `HashMap` of counts to find the most common type.
+    /// This method returns the `TypeId` of the type with the most elements, or `None` if the
+    /// purse is empty. Ties are broken the same way as in
+    /// [`types_by_frequency`](Purse::types_by_frequency).
     ///
     /// # Examples
     ///
@@ -235,10 +961,77 @@ impl Purse {
     /// assert_eq!(purse.most_common_type(), Some(TypeId::of::<&str>()));
     /// ```
     pub fn most_common_type(&self) -> Option<TypeId> {
-        self.counts.keys().max().copied()
+        self.types_by_frequency()
+            .first()
+            .map(|(type_id, _)| *type_id)
+    }
+    /// Lists the types stored in the purse along with their element counts, most common first.
+    ///
+    /// Types with equal counts are ordered by `TypeId`, so the order is stable for a given
+    /// build of the program.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// use std::any::TypeId;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1u8);
+    /// purse.insert("a");
+    /// purse.insert("b");
+    /// assert_eq!(
+    ///     purse.types_by_frequency(),
+    ///     vec![(TypeId::of::<&str>(), 2), (TypeId::of::<u8>(), 1)]
+    /// );
+    /// ```
+    pub fn types_by_frequency(&self) -> Vec<(TypeId, u64)> {
+        let mut types: Vec<(TypeId, u64)> = self
+            .non_empty_buckets()
+            .map(|(type_id, bucket)| (*type_id, bucket.len() as u64))
+            .collect();
+        types.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        types
+    }
+    /// Returns the column for `T`, if the purse has one.
+    fn column<T: Any>(&self) -> Option<&dyn Column<T>> {
+        self.data.get(&TypeId::of::<T>())?.column()
+    }
+    /// Mutable counterpart of [`column`](Purse::column).
+    fn column_mut<T: Any>(&mut self) -> Option<&mut dyn Column<T>> {
+        self.data.get_mut(&TypeId::of::<T>())?.column_mut()
+    }
+    /// Returns the column for `T`, creating it from the registered factory if needed.
+    fn column_or_default<T: Any>(&mut self) -> &mut dyn Column<T> {
+        let type_id = TypeId::of::<T>();
+        let factory = match self.factory(&type_id) {
+            Some(factory) => factory,
+            None if self.insertion_ordered => Bucket::sequenced::<T>,
+            None => Bucket::list::<T>,
+        };
+        self.data
+            .entry(type_id)
+            .or_insert_with(factory)
+            .column_mut()
+            .expect("buckets are keyed by the type of their column")
+    }
+    /// Returns the function creating buckets for the type registered as `type_id`.
+    fn factory(&self, type_id: &TypeId) -> Option<fn() -> Bucket> {
+        self.factories.get(type_id).copied()
+    }
+    /// Returns the function creating buckets for the type of `vtable`, whether registered
+    /// or not.
+    fn factory_or_default(&self, type_id: &TypeId, vtable: &VTable) -> fn() -> Bucket {
+        match self.factory(type_id) {
+            Some(factory) => factory,
+            None if self.insertion_ordered => vtable.sequenced,
+            None => vtable.empty,
+        }
     }
     /// Inserts an element into the purse.
     ///
+    /// The purse's [`Quota`] isn't checked. Use [`try_insert`](Purse::try_insert) to
+    /// keep within it.
+    ///
     /// # Examples
     /// ```
     /// # use purse::Purse;
@@ -247,10 +1040,77 @@ impl Purse {
     /// assert!(purse.contains(42));
     /// ```
     pub fn insert<T: Any>(&mut self, elem: T) {
-        let type_id = TypeId::of::<T>();
-        self.data.entry(type_id).or_default().push(Box::new(elem));
-
-        *self.counts.entry(type_id).or_insert(0) += 1;
+        self.column_or_default::<T>().insert(elem);
+    }
+    /// Inserts an element into the purse, recording how to clone, compare, hash and print it.
+    ///
+    /// A purse only knows its elements as `dyn Any`, so it can't implement `Clone`,
+    /// `PartialEq`, `Hash` or a useful `Debug` on its own. Inserting through this method
+    /// records those implementations for type `T`, after which every element of `T` takes part
+    /// in the purse's trait impls, including ones added later with plain
+    /// [`insert`](Purse::insert). The record survives calls to [`clear`](Purse::clear).
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element to insert. This type must implement [`ErasedValue`],
+    ///   that is `Any`, `Clone`, `Eq`, `Hash` and `Debug`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert_value(42);
+    /// purse.insert_value("hello");
+    ///
+    /// let snapshot = purse.clone();
+    /// assert_eq!(snapshot, purse);
+    /// purse.insert(7);
+    /// assert_ne!(snapshot, purse);
+    /// ```
+    pub fn insert_value<T: ErasedValue>(&mut self, elem: T) {
+        self.vtables
+            .entry(TypeId::of::<T>())
+            .or_insert_with(VTable::of::<T>);
+        self.insert(elem);
+    }
+    /// Inserts `n` copies of an element into the purse.
+    ///
+    /// Types registered with [`register_hashed`](Purse::register_hashed) or
+    /// [`register_ordered`](Purse::register_ordered) store the value once
+    /// and bump its multiplicity, so no clones are made. Otherwise the element is cloned
+    /// `n - 1` times. Inserting zero copies leaves the purse unchanged.
+    ///
+    /// Counted storage keeps multiplicities as `u64`, so on targets with a narrower
+    /// `usize` it can hold more copies than [`len`](Purse::len) and
+    /// [`count`](Purse::count) can report, which then saturate at `usize::MAX`.
+    /// [`count_of`](Purse::count_of) stays exact.
+    ///
+    /// The purse's [`Quota`] isn't checked. Use [`try_insert_n`](Purse::try_insert_n)
+    /// to keep within it.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element to insert. This type must implement `Any` and `Clone`.
+    ///
+    /// # Arguments
+    /// - `elem`: The element to insert.
+    /// - `n`: The number of copies to insert.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the copies don't fit in memory, for storage that keeps every copy.
+    ///
+    /// # Examples
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert_n("arrow", 20);
+    /// assert_eq!(purse.count_of(&"arrow"), 20);
+    /// assert_eq!(purse.count::<&str>(), 20);
+    /// ```
+    pub fn insert_n<T: Any + Clone>(&mut self, elem: T, n: u64) {
+        if n == 0 {
+            return;
+        }
+        self.column_or_default::<T>().insert_n(elem, n);
     }
     /// Removes a single occurrence of an element from the purse, if present.
     ///
@@ -275,24 +1135,241 @@ impl Purse {
     /// assert!(!purse.contains(&"apple"));
     /// ```
     pub fn remove<T: Any + Eq>(&mut self, elem: T) -> bool {
+        self.remove_n(&elem, 1) == 1
+    }
+    /// Removes a single element of type `T` that borrows as `value`, if present.
+    ///
+    /// This is the borrowed counterpart of [`remove`](Purse::remove), see
+    /// [`contains_borrowed`](Purse::contains_borrowed) for how the type parameters are used.
+    ///
+    /// # Returns
+    /// Returns `true` if an element was removed, or `false` if no such element was found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(vec![1u8, 2, 3]);
+    /// assert!(purse.remove_borrowed::<Vec<u8>, [u8]>(&[1, 2, 3]));
+    /// assert!(!purse.remove_borrowed::<Vec<u8>, [u8]>(&[1, 2, 3]));
+    /// ```
+    pub fn remove_borrowed<T, Q>(&mut self, value: &Q) -> bool
+    where
+        T: Any + Borrow<Q> + Hash + Eq,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(column) = self.column_mut::<T>() else {
+            return false;
+        };
+        if let Some(bag) = column
+            .as_any_mut()
+            .and_then(|c| c.downcast_mut::<HashedBag<T>>())
+        {
+            return bag.remove_n(value, 1) == 1;
+        }
+        column.remove_where(&mut |el| el.borrow() == value, 1) == 1
+    }
+    /// Removes up to `n` occurrences of an element from the purse.
+    ///
+    /// Occurrences are removed in insertion order, and the relative order of the remaining
+    /// elements is preserved.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element to remove. This type must implement `Any` and `Eq`.
+    ///
+    /// # Arguments
+    /// - `elem`: The element to remove from the purse.
+    /// - `n`: The maximum number of occurrences to remove.
+    ///
+    /// # Returns
+    /// The number of occurrences actually removed, which is less than `n` if the purse held
+    /// fewer copies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert_n("arrow", 5);
+    /// assert_eq!(purse.remove_n(&"arrow", 3), 3);
+    /// assert_eq!(purse.remove_n(&"arrow", 3), 2);
+    /// assert!(!purse.contains("arrow"));
+    /// ```
+    pub fn remove_n<T: Any + Eq>(&mut self, elem: &T, n: u64) -> u64 {
+        self.column_mut::<T>()
+            .map_or(0, |column| column.remove_n(elem, n))
+    }
+    /// Removes every occurrence of an element from the purse.
+    ///
+    /// # Returns
+    /// The number of occurrences removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert_n(7, 3);
+    /// purse.insert(8);
+    /// assert_eq!(purse.remove_all(&7), 3);
+    /// assert_eq!(purse.count::<i32>(), 1);
+    /// ```
+    pub fn remove_all<T: Any + Eq>(&mut self, elem: &T) -> u64 {
+        self.remove_n(elem, u64::MAX)
+    }
+    /// Removes and returns the most recently inserted element of a specific type.
+    ///
+    /// For types registered with [`register_hashed`](Purse::register_hashed), an arbitrary
+    /// element of the type is returned instead, and for types registered with
+    /// [`register_ordered`](Purse::register_ordered), the largest.
+    ///
+    /// # Returns
+    /// `Some(elem)` if the purse held an element of type `T`, otherwise `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1);
+    /// purse.insert(2);
+    /// assert_eq!(purse.pop::<i32>(), Some(2));
+    /// assert_eq!(purse.pop::<i32>(), Some(1));
+    /// assert_eq!(purse.pop::<i32>(), None);
+    /// ```
+    pub fn pop<T: Any>(&mut self) -> Option<T> {
+        self.column_mut::<T>().and_then(Column::pop)
+    }
+    /// Removes all elements of a specific type from the purse and returns them.
+    ///
+    /// Elements are returned in insertion order, except for types registered with
+    /// [`register_hashed`](Purse::register_hashed), which come in no particular order, and
+    /// [`register_ordered`](Purse::register_ordered), which come in ascending order. Elements
+    /// of other types are left in place.
+    ///
+    /// # Returns
+    /// A `Vec<T>` owning every element of type `T` that was in the purse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(String::from("foo"));
+    /// purse.insert(String::from("bar"));
+    /// purse.insert(42);
+    /// let strings: Vec<String> = purse.take_all_of_type();
+    /// assert_eq!(strings, vec!["foo", "bar"]);
+    /// assert_eq!(purse.count::<String>(), 0);
+    /// assert!(purse.contains(42));
+    /// ```
+    pub fn take_all_of_type<T: Any>(&mut self) -> Vec<T> {
         let type_id = TypeId::of::<T>();
-        if let Some(elems) = self.data.get_mut(&type_id) {
-            if let Some(index) = elems
-                .iter()
-                .position(|el| el.downcast_ref::<T>() == Some(&elem))
-            {
-                elems.remove(index);
-                *self.counts.entry(type_id).or_insert(0) =
-                    self.counts.entry(type_id).or_insert(0).saturating_sub(1);
-                return true;
+        self.data
+            .remove(&type_id)
+            .and_then(|mut bucket| Some(bucket.column_mut::<T>()?.take_all()))
+            .unwrap_or_default()
+    }
+    /// Removes all elements of a specific type from the purse, returning them as an iterator.
+    ///
+    /// This is equivalent to iterating over the result of
+    /// [`take_all_of_type`](Purse::take_all_of_type).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1u32);
+    /// purse.insert(2u32);
+    /// assert_eq!(purse.drain_type::<u32>().sum::<u32>(), 3);
+    /// assert_eq!(purse.count::<u32>(), 0);
+    /// ```
+    pub fn drain_type<T: Any>(&mut self) -> alloc::vec::IntoIter<T> {
+        self.take_all_of_type().into_iter()
+    }
+    /// Removes all elements from the purse, returning them as boxed values.
+    ///
+    /// The purse is empty once this method returns, even if the iterator is not fully consumed.
+    /// Storage registrations such as [`register_hashed`](Purse::register_hashed) are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(1);
+    /// purse.insert("two");
+    /// let items: Vec<_> = purse.drain().collect();
+    /// assert_eq!(items.len(), 2);
+    /// assert!(purse.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Box<dyn Iterator<Item = Box<dyn Any>> + '_> {
+        Box::new(
+            self.data
+                .drain()
+                .flat_map(|(_, bucket)| bucket.into_boxed_iter()),
+        )
+    }
+}
+
+impl<S: BuildHasher + Clone> Purse<S> {
+    /// Clones the purse, if every type it holds was inserted with
+    /// [`insert_value`](Purse::insert_value).
+    ///
+    /// This is the non-panicking counterpart of [`Clone::clone`].
+    ///
+    /// # Returns
+    /// `Some(purse)` with a deep copy of every element, or `None` if the purse holds an element
+    /// of a type it does not know how to clone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert_value(1);
+    /// assert!(purse.try_clone().is_some());
+    ///
+    /// struct Opaque;
+    /// purse.insert(Opaque);
+    /// assert!(purse.try_clone().is_none());
+    /// ```
+    pub fn try_clone(&self) -> Option<Self> {
+        self.clone_or_missing_type().ok()
+    }
+    /// Clones the purse, or returns the name of the first type that can't be cloned.
+    fn clone_or_missing_type(&self) -> Result<Self, &'static str> {
+        let mut data =
+            HashMap::with_capacity_and_hasher(self.data.len(), self.data.hasher().clone());
+        for (type_id, bucket) in &self.data {
+            match self.vtables.get(type_id) {
+                Some(vtable) => {
+                    let mut copy = self.factory_or_default(type_id, vtable)();
+                    (vtable.clone_into)(bucket, &mut copy);
+                    data.insert(*type_id, copy);
+                }
+                None if bucket.len() == 0 => {}
+                None => return Err(bucket.type_name()),
             }
         }
-        false
+        Ok(Self {
+            data,
+            factories: self.factories.clone(),
+            vtables: self.vtables.clone(),
+            insertion_ordered: self.insertion_ordered,
+            quota: self.quota,
+        })
     }
+}
+
+impl<S: BuildHasher> Purse<S> {
     /// Clears all elements from the purse.
     ///
     /// This method removes all elements from the purse, effectively resetting it to its initial state.
-    /// It clears both the `HashMap` storing the elements and the `HashMap` tracking the counts, requiring mutable access to the purse.
+    /// It clears the `HashMap` storing the elements, requiring mutable access to the purse.
+    /// Storage registrations such as [`register_hashed`](Purse::register_hashed) are kept.
     ///
     /// # Examples
     ///
@@ -305,17 +1382,127 @@ impl Purse {
     /// ```
     pub fn clear(&mut self) {
         self.data.clear();
-        self.counts.clear();
+    }
+}
+
+impl<S: BuildHasher + Clone> Clone for Purse<S> {
+    /// Returns a deep copy of the purse.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the purse holds an element of a type that was never inserted with
+    /// [`insert_value`](Purse::insert_value). Use [`try_clone`](Purse::try_clone) to handle
+    /// that case instead.
+    fn clone(&self) -> Self {
+        self.clone_or_missing_type().unwrap_or_else(|type_name| {
+            panic!(
+                "cannot clone a purse holding `{type_name}`, \
+                 which was not inserted with `insert_value`"
+            )
+        })
+    }
+}
+
+impl<S: BuildHasher> PartialEq for Purse<S> {
+    /// Two purses are equal if they hold the same elements with the same multiplicities,
+    /// regardless of order or storage.
+    ///
+    /// Elements can only be compared if both purses have recorded their type through
+    /// [`insert_value`](Purse::insert_value), so a purse holding elements of any other type
+    /// is unequal to every purse, including itself. For this reason `Purse` does not
+    /// implement `Eq`.
+    fn eq(&self, other: &Self) -> bool {
+        if self.len_types() != other.len_types() {
+            return false;
+        }
+        self.non_empty_buckets().all(|(type_id, a)| {
+            let (Some(vtable), Some(_), Some(b)) = (
+                self.vtables.get(type_id),
+                other.vtables.get(type_id),
+                other.data.get(type_id),
+            ) else {
+                return false;
+            };
+            (vtable.eq)(a, b)
+        })
+    }
+}
+
+impl<S: BuildHasher> Hash for Purse<S> {
+    /// Hashes the elements of every type in a way that is consistent with `PartialEq`.
+    ///
+    /// Elements of types never inserted with [`insert_value`](Purse::insert_value) only
+    /// contribute their type and count, which is consistent since such purses are unequal
+    /// to every purse.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut buckets: Vec<(&TypeId, &Bucket)> = self.non_empty_buckets().collect();
+        buckets.sort_unstable_by_key(|(type_id, _)| **type_id);
+        for (type_id, bucket) in buckets {
+            type_id.hash(state);
+            bucket.len().hash(state);
+            if let Some(vtable) = self.vtables.get(type_id) {
+                (vtable.hash)(bucket, state);
+            }
+        }
+    }
+}
+
+impl<S: BuildHasher> fmt::Debug for Purse<S> {
+    /// Prints the elements of each type, keyed by type name.
+    ///
+    /// Elements of types never inserted with [`insert_value`](Purse::insert_value) are only
+    /// shown as a count.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Items<'a>(&'a Bucket, Option<&'a VTable>);
+        impl fmt::Debug for Items<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.1 {
+                    Some(vtable) => (vtable.fmt)(self.0, f),
+                    None => write!(f, "<{} items>", self.0.len()),
+                }
+            }
+        }
+        f.debug_map()
+            .entries(self.ordered_buckets().into_iter().map(|(type_id, bucket)| {
+                (bucket.type_name(), Items(bucket, self.vtables.get(type_id)))
+            }))
+            .finish()
+    }
+}
+
+impl<S> IntoIterator for Purse<S> {
+    type Item = Box<dyn Any>;
+    type IntoIter = IntoIter;
+
+    /// Consumes the purse, yielding every element boxed as `dyn Any`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(7);
+    /// let item = purse.into_iter().next().unwrap();
+    /// assert_eq!(item.downcast_ref::<i32>(), Some(&7));
+    /// ```
+    fn into_iter(self) -> IntoIter {
+        IntoIter::new(Box::new(
+            self.data.into_values().flat_map(Bucket::into_boxed_iter),
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::{String, ToString};
+    use alloc::{format, vec};
+    use core::time::Duration;
 
     #[test]
     fn test_purse() {
         #[derive(PartialEq, Eq, Clone, Copy, Debug)]
+        #[allow(clippy::upper_case_acronyms)]
         enum RPS {
             Rock,
             Paper,
@@ -391,4 +1578,1362 @@ mod tests {
         assert_eq!(strs.first(), Some(&"foo"));
         assert_eq!(moves.first(), Some(&RPS::Paper));
     }
+
+    #[test]
+    fn test_hashed_storage() {
+        let mut purse = Purse::new();
+        purse.insert("foo");
+        purse.insert("bar");
+        purse.insert("foo");
+        purse.insert(5);
+        purse.register_hashed::<&str>();
+
+        // Existing items are migrated and counts are preserved.
+        assert_eq!(purse.count::<&str>(), 3);
+        let mut strings: Vec<&&str> = purse.get_all_of_type();
+        strings.sort();
+        assert_eq!(strings, vec![&"bar", &"foo", &"foo"]);
+        assert_eq!(purse.iter().count(), 4);
+
+        // Removal drops one occurrence at a time.
+        assert!(purse.remove("foo"));
+        assert!(purse.contains("foo"));
+        assert!(purse.remove("foo"));
+        assert!(!purse.contains("foo"));
+        assert!(!purse.remove("foo"));
+        assert_eq!(purse.count::<&str>(), 1);
+
+        // Registering again keeps the items.
+        purse.register_hashed::<&str>();
+        assert_eq!(purse.get_all_of_type::<&str>(), vec![&"bar"]);
+
+        // The registration survives clearing the purse.
+        purse.clear();
+        purse.insert("baz");
+        purse.insert("baz");
+        assert!(purse
+            .column::<&str>()
+            .and_then(Column::as_any)
+            .is_some_and(|column| column.is::<HashedBag<&str>>()));
+        assert_eq!(purse.get_all_of_type::<&str>(), vec![&"baz", &"baz"]);
+    }
+
+    #[test]
+    fn test_multiplicities() {
+        let mut purse = Purse::new();
+        purse.insert_n(1u8, 3);
+        purse.insert(2u8);
+        purse.insert_n(1u8, 2);
+        purse.insert_n(3u8, 0);
+        assert_eq!(purse.count_of(&1u8), 5);
+        assert_eq!(purse.count_of(&3u8), 0);
+        assert_eq!(purse.count::<u8>(), 6);
+
+        // Removing from the list storage keeps the remaining order.
+        assert_eq!(purse.remove_n(&1u8, 4), 4);
+        assert_eq!(purse.get_all_of_type::<u8>(), vec![&2, &1]);
+        assert_eq!(purse.count::<u8>(), 2);
+
+        // The same operations against hashed storage.
+        purse.register_hashed::<u8>();
+        purse.insert_n(1u8, 10);
+        assert_eq!(purse.count_of(&1u8), 11);
+        assert_eq!(purse.remove_n(&1u8, 4), 4);
+        assert_eq!(purse.remove_all(&1u8), 7);
+        assert_eq!(purse.remove_all(&1u8), 0);
+        assert_eq!(purse.count::<u8>(), 1);
+        assert!(purse.contains(2u8));
+
+        // Typed iteration over hashed storage knows its exact length.
+        purse.insert_n(4u8, 3);
+        let mut iter = purse.iter_of_type::<u8>();
+        assert_eq!(iter.len(), 4);
+        iter.next();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.count(), 3);
+    }
+
+    #[test]
+    fn test_owned_extraction() {
+        let mut purse = Purse::new();
+        purse.insert(String::from("a"));
+        purse.insert(String::from("b"));
+        purse.insert(1u8);
+        purse.register_hashed::<u8>();
+        purse.insert_n(2u8, 3);
+
+        assert_eq!(purse.pop::<String>(), Some(String::from("b")));
+        assert_eq!(purse.count::<String>(), 1);
+
+        // Hashed storage hands out clones of the stored value.
+        let mut bytes: Vec<u8> = purse.take_all_of_type();
+        bytes.sort();
+        assert_eq!(bytes, vec![1, 2, 2, 2]);
+        assert_eq!(purse.count::<u8>(), 0);
+        assert!(purse.as_slice::<u8>().is_some_and(<[u8]>::is_empty));
+
+        purse.insert_n(3u8, 2);
+        let drained: Vec<Box<dyn Any>> = purse.drain().collect();
+        assert_eq!(drained.len(), 3);
+        assert!(purse.is_empty());
+        assert_eq!(purse.count::<u8>(), 0);
+
+        // The hashed registration survives draining.
+        purse.insert(4u8);
+        assert_eq!(purse.as_slice::<u8>(), None);
+        let owned: Vec<u8> = purse
+            .into_iter()
+            .filter_map(|item| item.downcast::<u8>().ok())
+            .map(|item| *item)
+            .collect();
+        assert_eq!(owned, vec![4]);
+    }
+
+    #[test]
+    fn test_borrowed_lookup() {
+        let mut purse = Purse::new();
+        purse.insert(String::from("list"));
+        purse.insert(Box::new(5u32));
+        purse.register_hashed::<Box<u32>>();
+
+        assert!(purse.contains_borrowed::<String, str>("list"));
+        assert!(purse.contains_borrowed::<Box<u32>, u32>(&5));
+        assert!(!purse.contains_borrowed::<Box<u32>, u32>(&6));
+
+        assert!(purse.remove_borrowed::<String, str>("list"));
+        assert!(purse.remove_borrowed::<Box<u32>, u32>(&5));
+        assert!(!purse.remove_borrowed::<Box<u32>, u32>(&5));
+        assert_eq!(purse.count::<String>(), 0);
+        assert_eq!(purse.count::<Box<u32>>(), 0);
+    }
+
+    #[test]
+    fn test_ordered_storage() {
+        let mut purse = Purse::new();
+        purse.insert(5u32);
+        purse.insert(1u32);
+        purse.register_ordered::<u32>();
+        purse.insert_n(3u32, 2);
+        purse.insert(9u32);
+        assert!(purse
+            .column::<u32>()
+            .and_then(Column::as_any)
+            .is_some_and(|column| column.is::<sorted::SortedBag<u32>>()));
+
+        // Existing items were moved over and iteration is sorted.
+        assert_eq!(purse.get_all_of_type::<u32>(), vec![&1, &3, &3, &5, &9]);
+        assert_eq!(purse.as_slice::<u32>(), None);
+        assert_eq!(purse.min::<u32>(), Some(&1));
+        assert_eq!(purse.max::<u32>(), Some(&9));
+        assert_eq!(purse.rank(&5u32), 3);
+        assert_eq!(purse.nth_smallest::<u32>(2), Some(&3));
+        let range: Vec<_> = purse.range(2u32..=5).copied().collect();
+        assert_eq!(range, vec![3, 3, 5]);
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = purse.range(5u32..2);
+        assert_eq!(inverted.len(), 0);
+
+        // Multiset semantics are unchanged.
+        assert!(purse.contains(3u32));
+        assert_eq!(purse.count_of(&3u32), 2);
+        assert!(purse.remove(3u32));
+        assert_eq!(purse.count_of(&3u32), 1);
+        assert_eq!(purse.pop::<u32>(), Some(9));
+
+        // Switching to hashed storage keeps the items, and back again.
+        purse.register_hashed::<u32>();
+        assert_eq!(purse.count::<u32>(), 3);
+        purse.register_ordered::<u32>();
+        assert_eq!(purse.get_all_of_type::<u32>(), vec![&1, &3, &5]);
+
+        // Borrowed lookups fall back to a scan.
+        purse.register_ordered::<String>();
+        purse.insert(String::from("b"));
+        purse.insert(String::from("a"));
+        assert!(purse.contains_borrowed::<String, str>("a"));
+        assert!(purse.remove_borrowed::<String, str>("a"));
+        assert!(!purse.remove_borrowed::<String, str>("a"));
+        assert_eq!(purse.min::<String>().map(String::as_str), Some("b"));
+
+        // The same queries work on list storage.
+        purse.insert(2i8);
+        purse.insert(-4i8);
+        purse.insert(2i8);
+        assert_eq!(purse.min::<i8>(), Some(&-4));
+        assert_eq!(purse.rank(&2i8), 1);
+        assert_eq!(purse.nth_smallest::<i8>(2), Some(&2));
+        assert_eq!(purse.range(0i8..).len(), 2);
+
+        // Ordered types take part in the trait impls.
+        let mut a = Purse::new();
+        a.register_ordered::<u8>();
+        a.insert_value(2u8);
+        a.insert_value(1u8);
+        let b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(format!("{a:?}"), "{\"u8\": [1, 2]}");
+        let union = &a | &b;
+        assert_eq!(union.count::<u8>(), 2);
+    }
+
+    #[test]
+    fn test_column_strategies() {
+        /// Stores items in reverse insertion order, relying on the trait's defaults.
+        #[derive(Default)]
+        struct Stack(Vec<u16>);
+        impl Column<u16> for Stack {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+            fn insert(&mut self, value: u16) {
+                self.0.insert(0, value);
+            }
+            fn iter(&self) -> TypeIter<'_, u16> {
+                TypeIter::from_slice(&self.0)
+            }
+            fn remove_where(&mut self, matches: &mut dyn FnMut(&u16) -> bool, limit: u64) -> u64 {
+                let mut removed = 0;
+                self.0.retain(|elem| {
+                    let remove = removed < limit && matches(elem);
+                    removed += u64::from(remove);
+                    !remove
+                });
+                removed
+            }
+            fn pop(&mut self) -> Option<u16> {
+                (!self.0.is_empty()).then(|| self.0.remove(0))
+            }
+        }
+
+        let mut purse = Purse::new();
+        purse.insert(1u16);
+        purse.insert(2u16);
+        purse.register::<u16>(Strategy::custom::<Stack>());
+        purse.insert_n(3u16, 2);
+        assert_eq!(purse.get_all_of_type::<u16>(), vec![&3, &3, &2, &1]);
+        assert_eq!(purse.count_of(&3u16), 2);
+        assert_eq!(purse.min::<u16>(), Some(&1));
+        assert_eq!(purse.remove_n(&3u16, 5), 2);
+        assert_eq!(purse.pop::<u16>(), Some(2));
+        assert!(purse.iter_mut_of_type::<u16>().next().is_none());
+        assert_eq!(purse.take_all_of_type::<u16>(), vec![1]);
+
+        // The registration survives clearing, like the built-in strategies.
+        purse.insert(4u16);
+        purse.insert(5u16);
+        purse.clear();
+        purse.insert(4u16);
+        purse.insert(5u16);
+        assert_eq!(purse.get_all_of_type::<u16>(), vec![&5, &4]);
+
+        // A set ignores duplicates.
+        purse.register::<char>(Strategy::set());
+        purse.insert_n('a', 3);
+        purse.insert('b');
+        purse.insert('a');
+        assert_eq!(purse.count::<char>(), 2);
+        assert_eq!(purse.count_of(&'a'), 1);
+        assert_eq!(purse.remove_n(&'a', 2), 1);
+        assert!(!purse.contains('a'));
+
+        // Going back to a list keeps what is stored.
+        purse.register::<char>(Strategy::list());
+        purse.insert('b');
+        assert_eq!(purse.as_slice::<char>(), Some(&['b', 'b'][..]));
+
+        // Cloning and combining go through the registered columns.
+        let mut a = Purse::new();
+        a.register::<u8>(Strategy::set());
+        a.insert_value(1u8);
+        a.insert_value(1u8);
+        let b = a.clone();
+        assert_eq!(b.count::<u8>(), 1);
+        let sum = &a + &b;
+        assert_eq!(sum.count::<u8>(), 1);
+    }
+
+    #[test]
+    fn test_fixed_hasher() {
+        // Pinned, so that purses hash alike with and without the `std` feature.
+        let mut hasher = map::fixed_hasher();
+        1u8.hash(&mut hasher);
+        assert_eq!(hasher.finish(), 967_912_879_194_299_233);
+    }
+
+    #[test]
+    fn test_vtables() {
+        fn hash_of(purse: &Purse) -> u64 {
+            let mut hasher = map::fixed_hasher();
+            purse.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut a = Purse::new();
+        a.insert_value(1u8);
+        a.insert_value(2u8);
+        a.insert_value("x");
+        a.insert(1u8);
+
+        // Equality ignores insertion order and storage strategy.
+        let mut b = Purse::new();
+        b.register_hashed::<u8>();
+        b.insert_value("x");
+        b.insert_value(1u8);
+        b.insert_value(2u8);
+        b.insert_value(1u8);
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        let c = b.clone();
+        assert_eq!(c, a);
+        assert_eq!(c.count_of(&1u8), 2);
+        assert_eq!(c.as_slice::<u8>(), None);
+
+        b.remove(1u8);
+        assert_ne!(a, b);
+        assert_ne!(c, b);
+
+        let mut one = Purse::new();
+        one.insert_value(7i32);
+        assert_eq!(format!("{one:?}"), r#"{"i32": [7]}"#);
+
+        // Types without a vtable can't be compared or cloned.
+        one.insert(7u64);
+        assert_ne!(one, one);
+
+        // Equality is symmetric, and equal purses hash the same.
+        let mut recorded = Purse::new();
+        recorded.insert_value(1u8);
+        let mut unrecorded = Purse::new();
+        unrecorded.insert(1u8);
+        assert_ne!(recorded, unrecorded);
+        assert_ne!(unrecorded, recorded);
+        let mut other = Purse::new();
+        other.insert(1u8);
+        other.insert_value(1u8);
+        other.remove(1u8);
+        assert_eq!(recorded, other);
+        assert_eq!(other, recorded);
+        assert_eq!(hash_of(&recorded), hash_of(&other));
+        assert!(one.try_clone().is_none());
+        assert!(format!("{one:?}").contains(r#""u64": <1 items>"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot clone a purse holding `u64`")]
+    fn test_clone_without_vtable() {
+        let mut purse = Purse::new();
+        purse.insert(7u64);
+        let _ = purse.clone();
+    }
+
+    #[test]
+    fn test_multiset_algebra() {
+        let purse_of = |items: &[u8]| {
+            let mut purse = Purse::new();
+            items.iter().for_each(|&item| purse.insert_value(item));
+            purse
+        };
+        let a = purse_of(&[1, 1, 2, 3]);
+        let b = purse_of(&[1, 3, 3, 4]);
+
+        assert_eq!(&a | &b, purse_of(&[1, 1, 2, 3, 3, 4]));
+        assert_eq!(&a + &b, purse_of(&[1, 1, 1, 2, 3, 3, 3, 4]));
+        assert_eq!(&a & &b, purse_of(&[1, 3]));
+        assert_eq!(&a - &b, purse_of(&[1, 2]));
+        assert_eq!(&a ^ &b, purse_of(&[1, 2, 3, 4]));
+        assert!((&a & &b).is_subset(&a));
+        assert!(a.is_superset(&(&a & &b)));
+        assert!(!a.is_subset(&b));
+
+        // In-place operations remove from the front and append at the back.
+        let mut c = a.clone();
+        c -= &purse_of(&[1]);
+        c |= &b;
+        assert_eq!(c.as_slice::<u8>(), Some(&[1, 2, 3, 3, 4][..]));
+        assert_eq!(c.count::<u8>(), 5);
+
+        // Hashed storage on the left-hand side is kept.
+        let mut d = Purse::new();
+        d.register_hashed::<u8>();
+        d += &a;
+        d &= &b;
+        assert_eq!(d.as_slice::<u8>(), None);
+        assert_eq!(d, purse_of(&[1, 3]));
+
+        // Types without a vtable are fine as long as their values are not needed.
+        let mut e = purse_of(&[1]);
+        e.insert("opaque");
+        e -= &b;
+        assert_eq!(e.count::<&str>(), 1);
+        e &= &b;
+        assert_eq!(e.count::<&str>(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "inserting `u8` would exceed the limit of 1 elements")]
+    fn test_combine_over_quota() {
+        let mut x = Purse::new();
+        x.insert_value(1u8);
+        x.insert_value(2u8);
+        let mut y = Purse::new();
+        y.insert_value(2u8);
+        y.insert_value(3u8);
+
+        // Operations that don't grow the purse are fine over the quota.
+        x.set_quota(Quota {
+            max_len: Some(1),
+            ..Quota::default()
+        });
+        x &= &y;
+        assert_eq!(x.len(), 1);
+        x |= &y;
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot combine purses holding `&str`")]
+    fn test_combine_without_vtable() {
+        let mut a = Purse::new();
+        a.insert("opaque");
+        let mut b = Purse::new();
+        b.insert("opaque");
+        a -= &b;
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_thread_safe_purses() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SendPurse>();
+        assert_send_sync::<ConcurrentPurse>();
+
+        let mut purse = SendPurse::new();
+        purse.register_hashed::<String>();
+        purse.insert_value(String::from("a"));
+        purse.insert_n(1u64, 3);
+        let purse = std::thread::spawn(move || {
+            let mut purse = purse;
+            assert!(purse.remove_borrowed::<String, str>("a"));
+            purse.insert(2u64);
+            purse
+        })
+        .join()
+        .unwrap();
+        assert_eq!(purse.count::<u64>(), 4);
+        assert!(!purse.contains_borrowed::<String, str>("a"));
+        let purse: Purse = purse.into();
+        assert_eq!(purse.len_types(), 1);
+
+        let purse = ConcurrentPurse::new();
+        purse.register_hashed::<u64>();
+        std::thread::scope(|s| {
+            for t in 0..8u64 {
+                let purse = &purse;
+                s.spawn(move || {
+                    for n in 0..100u64 {
+                        purse.insert(n % 10);
+                        purse.insert_n(t as u8, 2);
+                    }
+                    for n in 0..5u64 {
+                        assert!(purse.contains(n));
+                        assert!(purse.remove(n));
+                    }
+                });
+            }
+        });
+        assert_eq!(purse.count::<u64>(), 8 * 100 - 8 * 5);
+        assert_eq!(purse.count_of(&0u64), 80 - 8);
+        assert_eq!(purse.count::<u8>(), 8 * 200);
+        assert_eq!(purse.remove_n(&3u8, 1000), 200);
+        assert_eq!(purse.pop::<i32>(), None);
+        assert!(!purse.remove(0i32));
+        assert_eq!(purse.len_types(), 2);
+
+        purse.clear();
+        assert!(purse.is_empty());
+        purse.insert(5u64);
+        let mut purse = purse.into_purse();
+        assert_eq!(purse.count::<u64>(), 1);
+        // The hashed registration made it into the merged purse.
+        purse.insert_n(5u64, 1 << 40);
+        assert_eq!(purse.count_of(&5u64), (1 << 40) + 1);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    #[should_panic(expected = "must be made with `Strategy::custom_send`")]
+    fn test_send_purse_custom_strategy() {
+        /// A list that isn't `Send` or `Sync`, whatever it stores.
+        struct Local<T>(Vec<T>, core::marker::PhantomData<*const ()>);
+        impl<T> Default for Local<T> {
+            fn default() -> Self {
+                Self(Vec::new(), core::marker::PhantomData)
+            }
+        }
+        impl<T: 'static> Column<T> for Local<T> {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+            fn insert(&mut self, value: T) {
+                self.0.push(value);
+            }
+            fn iter(&self) -> TypeIter<'_, T> {
+                TypeIter::new(self.0.iter())
+            }
+            fn remove_where(&mut self, _: &mut dyn FnMut(&T) -> bool, _: u64) -> u64 {
+                0
+            }
+            fn pop(&mut self) -> Option<T> {
+                self.0.pop()
+            }
+        }
+        /// A list that is `Send` and `Sync`.
+        #[derive(Default)]
+        struct Shared(Vec<u8>);
+        impl Column<u8> for Shared {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+            fn insert(&mut self, value: u8) {
+                self.0.push(value);
+            }
+            fn iter(&self) -> TypeIter<'_, u8> {
+                TypeIter::from_slice(&self.0)
+            }
+            fn remove_where(&mut self, _: &mut dyn FnMut(&u8) -> bool, _: u64) -> u64 {
+                0
+            }
+            fn pop(&mut self) -> Option<u8> {
+                self.0.pop()
+            }
+        }
+
+        // A plain purse takes any column.
+        let mut purse = Purse::new();
+        purse.register::<u8>(Strategy::custom::<Local<u8>>());
+        purse.insert_n(1u8, 2);
+        let iter = purse.iter_of_type::<u8>();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.clone().count(), 2);
+
+        let mut send = SendPurse::new();
+        send.register::<u8>(Strategy::custom_send::<Shared>());
+        send.insert(1u8);
+        assert_eq!(send.count::<u8>(), 1);
+        send.register::<u16>(Strategy::custom::<Local<u16>>());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_mailbox() {
+        use std::time::{Duration, Instant};
+
+        let mailbox = Mailbox::new();
+        let start = Instant::now();
+        assert_eq!(mailbox.take_timeout::<u32>(Duration::from_millis(20)), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        std::thread::scope(|s| {
+            let consumers: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| (0..25).map(|_| mailbox.take_blocking::<u32>()).sum::<u32>()))
+                .collect();
+            let timed = s.spawn(|| mailbox.take_timeout::<String>(Duration::from_secs(60)));
+            for n in 0..100u32 {
+                mailbox.insert(n);
+                mailbox.insert(n as u8);
+            }
+            mailbox.insert(String::from("done"));
+            let total: u32 = consumers.into_iter().map(|c| c.join().unwrap()).sum();
+            assert_eq!(total, (0..100).sum());
+            assert_eq!(timed.join().unwrap().as_deref(), Some("done"));
+        });
+
+        // Messages of each type come out in the order they went in.
+        assert_eq!(mailbox.count::<u8>(), 100);
+        assert_eq!(mailbox.try_take::<u8>(), Some(0));
+        assert_eq!(mailbox.take_blocking::<u8>(), 1);
+        assert_eq!(mailbox.try_take::<u32>(), None);
+        let rest = mailbox.into_purse();
+        assert_eq!(rest.len(), 98);
+        assert_eq!(rest.iter_of_type::<u8>().next(), Some(&2));
+
+        // Messages only need to be `Send`.
+        let mailbox = Mailbox::new();
+        std::thread::scope(|s| {
+            s.spawn(|| mailbox.insert(core::cell::Cell::new(7u8)));
+        });
+        assert_eq!(mailbox.take_blocking::<core::cell::Cell<u8>>().get(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_wait_for() {
+        use std::future::Future;
+        use std::pin::pin;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        #[derive(Default)]
+        struct CountWakes(AtomicUsize);
+        impl Wake for CountWakes {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mailbox = Mailbox::new();
+        let wakes = Arc::new(CountWakes::default());
+        let waker = Waker::from(Arc::clone(&wakes));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut first = pin!(mailbox.wait_for::<u32>());
+        let mut second = pin!(mailbox.wait_for::<u32>());
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Pending);
+
+        // Other types don't wake the waiters.
+        mailbox.insert("unrelated");
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+        // Every waiter is woken once, and the first to be polled gets the message.
+        mailbox.insert(7u32);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 2);
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(7));
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Pending);
+        mailbox.insert(8u32);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 3);
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Ready(8));
+
+        // A future dropped before it resolves takes its waker with it.
+        {
+            let mut dropped = pin!(mailbox.wait_for::<u16>());
+            assert_eq!(dropped.as_mut().poll(&mut cx), Poll::Pending);
+        }
+        mailbox.insert(1u16);
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 3);
+        assert_eq!(mailbox.try_take::<u16>(), Some(1));
+
+        // Messages already waiting resolve the future right away.
+        mailbox.insert(9u32);
+        let mut ready = pin!(mailbox.wait_for::<u32>());
+        assert_eq!(ready.as_mut().poll(&mut cx), Poll::Ready(9));
+        assert_eq!(mailbox.len(), 1);
+    }
+
+    #[test]
+    fn test_item_handles() {
+        let mut purse = Purse::new();
+        purse.insert(String::from("a"));
+        let a = purse.insert_with_handle(String::from("a"));
+        let b = purse.insert_with_handle(String::from("b"));
+        // Elements inserted before the switch to keyed storage are kept.
+        assert_eq!(purse.count::<String>(), 3);
+        assert_eq!(purse.count_of(&String::from("a")), 2);
+
+        purse.get_mut(a).unwrap().push('!');
+        assert_eq!(purse.get(a).map(String::as_str), Some("a!"));
+        assert_eq!(purse.remove_by_handle(a).as_deref(), Some("a!"));
+        assert_eq!(purse.remove_by_handle(a), None);
+        assert_eq!(purse.get_mut(a), None);
+
+        // The slot of `a` is reused, but its handle stays stale.
+        let c = purse.insert_with_handle(String::from("c"));
+        assert_ne!(a, c);
+        assert_eq!(purse.get(a), None);
+        assert_eq!(purse.get(c).map(String::as_str), Some("c"));
+
+        // Removing by value also invalidates the handle.
+        assert!(purse.remove(String::from("b")));
+        assert_eq!(purse.get(b), None);
+        purse
+            .iter_mut_of_type::<String>()
+            .for_each(|s| s.make_ascii_uppercase());
+        assert_eq!(purse.iter_of_type::<String>().len(), 2);
+        assert_eq!(purse.get(c).map(String::as_str), Some("C"));
+
+        // So do clearing the purse, taking the type out and changing its storage.
+        purse.clear();
+        let d = purse.insert_with_handle(String::from("d"));
+        assert_eq!(purse.get(c), None);
+        assert_eq!(purse.take_all_of_type::<String>(), vec![String::from("d")]);
+        assert_eq!(purse.get(d), None);
+        let e = purse.insert_with_handle(String::from("e"));
+        purse.register(Strategy::<String>::keyed());
+        assert_eq!(purse.get(e), None);
+        assert_eq!(purse.count::<String>(), 1);
+
+        // Handles don't carry over to clones or other purses.
+        let mut other = Purse::new();
+        let f = other.insert_with_handle(String::from("f"));
+        assert_eq!(purse.get(f), None);
+        let mut copy = Purse::new();
+        copy.insert_value(1u8);
+        let one = copy.insert_with_handle(1u8);
+        assert_eq!(copy.clone().get(one), None);
+        assert_eq!(copy.get(one), Some(&1));
+        assert_eq!(copy.get::<u16>(ItemHandle::new(0, 0)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "has no item handles")]
+    fn test_item_handles_need_keyed_storage() {
+        let mut purse = Purse::new();
+        purse.register_hashed::<u8>();
+        purse.insert_with_handle(1u8);
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        fn items(purse: &Purse) -> Vec<String> {
+            purse
+                .iter()
+                .map(|elem| match elem.downcast_ref::<u8>() {
+                    Some(n) => n.to_string(),
+                    None => elem.downcast_ref::<&str>().unwrap().to_string(),
+                })
+                .collect()
+        }
+
+        let mut purse = Purse::insertion_ordered();
+        assert!(purse.is_insertion_ordered());
+        assert!(purse.first().is_none() && purse.last().is_none());
+        purse.insert_value("a");
+        purse.insert_value(1u8);
+        purse.insert_value("b");
+        purse.insert_value(2u8);
+        purse.insert_value(1u8);
+        assert_eq!(items(&purse), ["a", "1", "b", "2", "1"]);
+        assert_eq!(purse.get_all_of_type::<u8>(), vec![&1, &2, &1]);
+
+        // Removals keep the order of what is left.
+        assert!(purse.remove(1u8));
+        assert!(purse.remove("a"));
+        assert_eq!(items(&purse), ["b", "2", "1"]);
+        assert_eq!(purse.types(), [TypeId::of::<&str>(), TypeId::of::<u8>()]);
+        purse.iter_mut_of_type::<u8>().for_each(|n| *n += 10);
+        assert_eq!(items(&purse), ["b", "12", "11"]);
+
+        // Clones keep the order across types, and so does the union built from one.
+        let copy = purse.clone();
+        assert!(copy.is_insertion_ordered());
+        assert_eq!(items(&copy), ["b", "12", "11"]);
+        let mut other = Purse::new();
+        other.insert_value("c");
+        let union = &copy | &other;
+        assert_eq!(items(&union), ["b", "12", "11", "c"]);
+
+        // Registered types come last, in the order of their storage.
+        purse.insert_value(0u8);
+        purse.register_ordered::<u8>();
+        purse.insert_value("d");
+        assert_eq!(items(&purse), ["b", "d", "0", "11", "12"]);
+        assert_eq!(purse.first().and_then(|e| e.downcast_ref()), Some(&"b"));
+        assert_eq!(purse.last().and_then(|e| e.downcast_ref()), Some(&12u8));
+        assert_eq!(
+            format!("{purse:?}"),
+            r#"{"&str": ["b", "d"], "u8": [0, 11, 12]}"#
+        );
+
+        // Clearing forgets the order, but not the mode.
+        purse.clear();
+        purse.insert("e");
+        assert_eq!(purse.last().and_then(|e| e.downcast_ref()), Some(&"e"));
+
+        // Elements with handles keep their place, even in reused slots, and so do the
+        // elements of types registered as lists.
+        let mut purse = Purse::insertion_ordered();
+        purse.insert_value(1u8);
+        purse.insert_value("x");
+        let two = purse.insert_with_handle(2u8);
+        purse.insert_value("y");
+        assert_eq!(items(&purse), ["1", "x", "2", "y"]);
+        let three = purse.insert_with_handle(3u8);
+        assert_eq!(purse.remove_by_handle(two), Some(2));
+        purse.insert_with_handle(4u8);
+        assert_eq!(purse.get_all_of_type::<u8>(), vec![&1, &4, &3]);
+        assert_eq!(items(&purse), ["1", "x", "y", "3", "4"]);
+        assert_eq!(purse.first().and_then(|e| e.downcast_ref()), Some(&1u8));
+        assert_eq!(purse.last().and_then(|e| e.downcast_ref()), Some(&4u8));
+        assert_eq!(purse.get(three), Some(&3));
+        assert_eq!(items(&purse.clone()), ["1", "x", "y", "3", "4"]);
+        purse.register::<u8>(Strategy::list());
+        purse.register::<&str>(Strategy::list());
+        purse.insert_value("z");
+        assert_eq!(items(&purse), ["1", "x", "y", "3", "4", "z"]);
+        assert_eq!(purse.as_slice::<u8>(), Some(&[1u8, 3, 4][..]));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_hashers() {
+        use std::collections::hash_map::{DefaultHasher, RandomState};
+        use std::hash::BuildHasherDefault;
+
+        let mut hasher = TypeIdHasher::default();
+        hasher.write(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(hasher.finish(), 1);
+        let mut hasher = TypeIdHasher::default();
+        TypeId::of::<u8>().hash(&mut hasher);
+        let mut other = TypeIdHasher::default();
+        TypeId::of::<u16>().hash(&mut other);
+        assert_ne!(hasher.finish(), other.finish());
+
+        let mut purse: Purse<RandomState> = Purse::with_hasher(RandomState::new());
+        purse.register::<String>(Strategy::hashed_with_hasher::<
+            BuildHasherDefault<DefaultHasher>,
+        >());
+        purse.insert_value(String::from("a"));
+        purse.insert_n(String::from("b"), 2);
+        purse.insert_value(1u8);
+        assert_eq!(purse.count_of(&String::from("b")), 2);
+        assert!(purse.contains_borrowed::<String, str>("a"));
+        assert!(purse.remove_borrowed::<String, str>("b"));
+        assert_eq!(purse.count::<String>(), 2);
+
+        let copy = purse.clone();
+        assert_eq!(copy, purse);
+        let union = &copy | &purse;
+        assert_eq!(union.len(), 3);
+        assert_eq!(
+            format!("{:?}", Purse::with_hasher(RandomState::new())),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_zero_sized_types() {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        struct Tick;
+
+        let with = |strategy| {
+            let mut purse = Purse::new();
+            purse.register::<Tick>(strategy);
+            purse
+        };
+        let purses = [
+            ("list", Purse::new()),
+            ("sequenced", Purse::insertion_ordered()),
+            ("keyed", with(Strategy::keyed())),
+            ("hashed", with(Strategy::hashed())),
+            ("ordered", with(Strategy::ordered())),
+            ("set", with(Strategy::set())),
+        ];
+        for (name, mut purse) in purses {
+            purse.insert_n(Tick, 1_000);
+            let expected = if name == "set" { 1 } else { 1_000 };
+            assert_eq!(purse.count::<Tick>(), expected, "{name}");
+            let iterated = purse.iter_of_type::<Tick>().count() as u64;
+            assert_eq!(iterated, expected, "{name}");
+            assert!(purse.contains(Tick), "{name}");
+            let values = purse.memory_usage().of::<Tick>().unwrap().values;
+            match name {
+                "list" => assert_eq!(values, 0),
+                "hashed" | "ordered" | "set" => assert!(values < 1_000, "{name}"),
+                _ => assert!(values >= 1_000 * 8, "{name}"),
+            }
+
+            assert!(purse.remove(Tick), "{name}");
+            let popped = purse.pop::<Tick>();
+            assert_eq!(popped, (expected > 1).then_some(Tick), "{name}");
+            let rest = expected.saturating_sub(2) as usize;
+            assert_eq!(purse.take_all_of_type::<Tick>().len(), rest, "{name}");
+            assert!(!purse.contains(Tick), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut purse = Purse::with_capacity(4);
+        purse.register_hashed::<String>();
+        purse.register_ordered::<i64>();
+        purse.register::<char>(Strategy::keyed());
+        purse.reserve::<u32>(50);
+        purse.reserve::<String>(50);
+        purse.reserve::<char>(50);
+        assert!(purse.is_empty());
+        assert!(purse.capacity::<u32>() >= 50);
+        assert!(purse.capacity::<String>() >= 50);
+        assert!(purse.capacity::<char>() >= 50);
+        assert_eq!(purse.capacity::<i64>(), 0);
+
+        purse.insert_n(String::from("a"), 3);
+        purse.insert_n(7i64, 2);
+        purse.insert(1u32);
+        purse.insert(());
+        let usage = purse.memory_usage();
+        assert_eq!(usage.types.len(), 5);
+        assert!(usage.of::<u32>().unwrap().values >= 50 * 4);
+        assert_eq!(usage.of::<()>().unwrap().values, 0);
+        assert!(usage.of::<u8>().is_none());
+        let strings = usage.of::<String>().unwrap();
+        assert_eq!(
+            (strings.type_name, strings.len),
+            ("alloc::string::String", 3)
+        );
+        assert!(strings.map_entries > usage.of::<u32>().unwrap().map_entries);
+        assert!(usage.total() >= usage.maps + strings.values + strings.boxes);
+
+        purse.remove(1u32);
+        purse.shrink_to_fit();
+        assert_eq!(purse.capacity::<u32>(), 0);
+        assert!((1..50).contains(&purse.capacity::<String>()));
+        assert_eq!(purse.capacity::<i64>(), 1);
+        assert_eq!(purse.capacity::<char>(), 0);
+        assert_eq!(purse.count::<String>(), 3);
+        let shrunk = purse.memory_usage();
+        assert_eq!(shrunk.types.len(), 3);
+        assert!(shrunk.total() < usage.total());
+        purse.insert('x');
+        assert_eq!(purse.count::<char>(), 1);
+        let handle = purse.insert_with_handle('y');
+        assert_eq!(purse.get(handle), Some(&'y'));
+    }
+
+    #[test]
+    fn test_quota() {
+        let mut purse = Purse::new();
+        assert_eq!(purse.quota(), Quota::default());
+        purse.set_quota(Quota {
+            max_len: Some(5),
+            max_per_type: Some(3),
+            max_bytes: Some(14),
+        });
+        purse.insert_n(1u8, 3);
+        let err = purse.try_insert(2u8).unwrap_err();
+        assert_eq!(
+            err,
+            PurseError::QuotaExceeded {
+                type_name: "u8",
+                limit: Limit::PerType(3),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "inserting `u8` would exceed the limit of 3 elements per type"
+        );
+        // 3 bytes of `u8`s leave room for one `u64`, but not two.
+        assert_eq!(
+            purse.try_insert_n(1u64, 2),
+            Err(PurseError::QuotaExceeded {
+                type_name: "u64",
+                limit: Limit::Bytes(14),
+            })
+        );
+        assert_eq!(purse.count::<u64>(), 0);
+        assert!(purse.try_insert(1u64).is_ok());
+        assert!(purse.try_insert('a').is_err());
+        assert!(purse.try_insert(()).is_ok());
+        assert_eq!(
+            purse.try_insert(()).unwrap_err(),
+            PurseError::QuotaExceeded {
+                type_name: "()",
+                limit: Limit::Len(5),
+            }
+        );
+        assert_eq!(purse.len(), 5);
+
+        // Registering moves elements over without counting them again.
+        purse.set_quota(Quota {
+            max_len: Some(1),
+            ..purse.quota()
+        });
+        purse.register_hashed::<u8>();
+        assert_eq!(purse.count::<u8>(), 3);
+        purse.remove(1u8);
+        assert!(purse.try_insert(1u8).is_err());
+        // Inserting no copies fits even in a purse over its quota.
+        assert_eq!(purse.try_insert_n(1u8, 0), Ok(()));
+        purse.insert_n(1u8, 0);
+        assert_eq!(purse.len(), 4);
+
+        purse.clear();
+        let mut copy = purse.clone();
+        assert_eq!(copy.quota(), purse.quota());
+        copy.set_quota(Quota::default());
+        copy.insert_n(9u8, 100);
+        assert_eq!(copy.count::<u8>(), 100);
+    }
+
+    #[test]
+    fn test_bounded_purse() {
+        let evicted_values = |evicted: Vec<Box<dyn Any>>| -> Vec<u32> {
+            evicted
+                .into_iter()
+                .map(|e| *e.downcast().unwrap())
+                .collect()
+        };
+        let quota = Quota {
+            max_len: Some(4),
+            max_per_type: Some(3),
+            ..Quota::default()
+        };
+
+        let mut fifo = BoundedPurse::new(quota, Eviction::Oldest);
+        let handles: Vec<_> = (0..3u32).map(|i| fifo.insert(i).0).collect();
+        assert_eq!(fifo.get(handles[0]), Some(&0));
+        let (_, evicted) = fifo.insert(3u32);
+        assert_eq!(evicted_values(evicted), [0]);
+        let (_, evicted) = fifo.insert('a');
+        assert!(evicted.is_empty());
+        let (_, evicted) = fifo.insert('b');
+        assert_eq!(evicted_values(evicted), [1]);
+        assert_eq!(fifo.len(), 4);
+        assert_eq!(fifo.remove_by_handle(handles[2]), Some(2));
+        let (_, evicted) = fifo.insert('c');
+        assert!(evicted.is_empty());
+        assert_eq!(fifo.get_all_of_type::<char>(), [&'a', &'b', &'c']);
+
+        let mut lru = BoundedPurse::new(quota, Eviction::LeastRecentlyUsed);
+        let handles: Vec<_> = (0..3u32).map(|i| lru.insert(i).0).collect();
+        *lru.get_mut_and_touch(handles[0]).unwrap() += 10;
+        assert!(lru.touch(handles[1]));
+        let (_, evicted) = lru.insert(3u32);
+        assert_eq!(evicted_values(evicted), [2]);
+        assert!(!lru.touch(handles[2]));
+        let (_, evicted) = lru.insert(4u32);
+        assert_eq!(evicted_values(evicted), [10]);
+        // Reads through `Deref` aren't accesses.
+        assert_eq!(lru.get(handles[1]), Some(&1));
+        assert!(lru.contains(1u32));
+        let (_, evicted) = lru.insert(5u32);
+        assert_eq!(evicted_values(evicted), [1]);
+
+        let mut random = BoundedPurse::new(quota, Eviction::Random);
+        let mut evicted = 0;
+        for i in 0..100u32 {
+            evicted += random.insert(i).1.len();
+            evicted += random.insert(i as u16).1.len();
+            assert!(random.len() <= 4);
+        }
+        assert_eq!(evicted, 196);
+        assert_eq!(random.eviction(), Eviction::Random);
+
+        let mut nothing = BoundedPurse::new(
+            Quota {
+                max_bytes: Some(0),
+                ..Quota::default()
+            },
+            Eviction::Oldest,
+        );
+        let (handle, evicted) = nothing.insert(7u32);
+        assert_eq!(evicted_values(evicted), [7]);
+        assert_eq!(nothing.get(handle), None);
+        nothing.insert(());
+        assert_eq!(nothing.len(), 1);
+        nothing.clear();
+        assert!(nothing.is_empty());
+        assert!(nothing.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_expiring_purse() {
+        let clock = ManualClock::new();
+        let mut purse = ExpiringPurse::with_clock(&clock);
+        let token = purse.insert_with_ttl("token", Duration::from_secs(30));
+        let marker = purse.insert_with_ttl((), Duration::from_secs(10));
+        purse.insert_with_ttl((), Duration::from_secs(20));
+        purse.insert_with_ttl("token", Duration::ZERO);
+        purse.insert_with_ttl(1u8, Duration::MAX);
+        purse.insert(());
+        assert_eq!(purse.expires_in(token), Some(Duration::from_secs(30)));
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(purse.len(), 6);
+        assert_eq!(purse.get(marker), Some(&()));
+        purse.set_hide_expired(true);
+        assert!(purse.hides_expired());
+        assert_eq!(purse.len(), 4);
+        assert_eq!(purse.iter().count(), 4);
+        // Expired elements are told apart by their handles, even zero-sized ones.
+        assert_eq!(purse.iter().filter(|elem| elem.is::<()>()).count(), 2);
+        assert_eq!(purse.count::<()>(), 2);
+        assert_eq!(purse.count_of(&"token"), 1);
+        assert!(purse.contains(1u8));
+        assert_eq!(purse.get(marker), None);
+        assert_eq!(purse.expires_in(marker), Some(Duration::ZERO));
+        assert_eq!(purse.expires_in(token), Some(Duration::from_secs(20)));
+
+        let expired = purse.expire();
+        assert_eq!(expired.len(), 2);
+        assert_eq!(expired[0].downcast_ref::<&str>(), Some(&"token"));
+        assert!(expired[1].is::<()>());
+        assert_eq!(purse.count::<()>(), 2);
+        assert!(purse.expire().is_empty());
+
+        assert_eq!(purse.remove_by_handle(token), Some("token"));
+        assert_eq!(purse.expires_in(token), None);
+        clock.advance(Duration::from_secs(3600));
+        assert!(!purse.contains("token"));
+        assert_eq!(purse.len(), 2);
+        assert_eq!(purse.expire().len(), 1);
+        assert_eq!(purse.count::<u8>(), 1);
+        purse.clear();
+        assert!(purse.is_empty());
+    }
+
+    #[test]
+    fn test_sliding_purse() {
+        let mut last = SlidingPurse::with_clock(Window::Inserts(3), ManualClock::new());
+        for i in 0..5u8 {
+            last.insert(i % 2);
+            last.insert(());
+        }
+        assert_eq!(last.len(), 3);
+        assert_eq!(last.count::<()>(), 2);
+        assert_eq!(last.count_of(&0u8), 1);
+        assert!(!last.contains(1u8));
+        assert_eq!(last.iter().count(), 3);
+        assert_eq!(last.set_window(Window::Inserts(0)), 3);
+        assert!(last.is_empty());
+
+        let clock = ManualClock::new();
+        let mut recent = SlidingPurse::with_clock(Window::Time(Duration::from_secs(10)), &clock);
+        for _ in 0..4 {
+            recent.insert("hit");
+            recent.insert(());
+            clock.advance(Duration::from_secs(4));
+        }
+        // Inserted 16s, 12s, 8s and 4s ago, the last insertion dropped the first pair.
+        assert_eq!(Purse::len(&recent), 6);
+        assert_eq!(recent.len(), 4);
+        assert_eq!(recent.count::<&str>(), 2);
+        assert_eq!(recent.count::<()>(), 2);
+        assert_eq!(recent.count_of(&"hit"), 2);
+        assert_eq!(recent.iter().count(), 4);
+        assert_eq!(recent.iter().filter(|elem| elem.is::<()>()).count(), 2);
+        assert_eq!(recent.prune(), 2);
+        assert_eq!(Purse::len(&recent), 4);
+
+        clock.advance(Duration::from_secs(10));
+        assert!(!recent.contains("hit"));
+        recent.insert("hit");
+        assert_eq!(recent.len(), 1);
+        assert_eq!(Purse::len(&recent), 1);
+        assert_eq!(recent.window(), Window::Time(Duration::from_secs(10)));
+        recent.clear();
+        assert!(recent.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_insert_over_quota() {
+        let mut purse = Purse::new();
+        purse.set_quota(Quota {
+            max_len: Some(2),
+            ..Quota::default()
+        });
+        purse.insert(1);
+        purse.insert_with_handle(2);
+        purse.insert("three");
+        purse.insert_n(4u8, 2);
+        assert_eq!(purse.len(), 5);
+        assert!(purse.try_insert(6).is_err());
+    }
+
+    mod accounting {
+        use super::*;
+        use proptest::prelude::*;
+        // Shadows the purse's `Strategy`, which both globs would otherwise bring in.
+        use proptest::strategy::Strategy;
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        enum Kind {
+            U8,
+            U16,
+            Str,
+        }
+
+        #[derive(Clone, Debug)]
+        enum Op {
+            Insert(Kind, u8),
+            InsertN(Kind, u8, u8),
+            Remove(Kind, u8),
+            RemoveAll(Kind, u8),
+            Pop(Kind),
+            TakeAll(Kind),
+            RegisterHashed(Kind),
+            RegisterOrdered(Kind),
+            RegisterList(Kind),
+            RegisterKeyed(Kind),
+            Clear,
+        }
+
+        fn kind() -> impl Strategy<Value = Kind> {
+            prop_oneof![Just(Kind::U8), Just(Kind::U16), Just(Kind::Str)]
+        }
+
+        fn op() -> impl Strategy<Value = Op> {
+            prop_oneof![
+                4 => (kind(), 0..4u8).prop_map(|(k, v)| Op::Insert(k, v)),
+                2 => (kind(), 0..4u8, 0..4u8).prop_map(|(k, v, n)| Op::InsertN(k, v, n)),
+                3 => (kind(), 0..4u8).prop_map(|(k, v)| Op::Remove(k, v)),
+                1 => (kind(), 0..4u8).prop_map(|(k, v)| Op::RemoveAll(k, v)),
+                2 => kind().prop_map(Op::Pop),
+                1 => kind().prop_map(Op::TakeAll),
+                1 => kind().prop_map(Op::RegisterHashed),
+                1 => kind().prop_map(Op::RegisterOrdered),
+                1 => kind().prop_map(Op::RegisterList),
+                1 => kind().prop_map(Op::RegisterKeyed),
+                1 => Just(Op::Clear),
+            ]
+        }
+
+        /// Applies `op` to both the purse and the reference model, which keeps the values
+        /// of one type as a plain list.
+        fn apply<T: Any + Clone + Hash + Ord>(
+            purse: &mut Purse,
+            model: &mut Vec<u8>,
+            op: &Op,
+            make: fn(u8) -> T,
+        ) {
+            match *op {
+                Op::Insert(_, v) => {
+                    purse.insert(make(v));
+                    model.push(v);
+                }
+                Op::InsertN(_, v, n) => {
+                    purse.insert_n(make(v), u64::from(n));
+                    model.extend(core::iter::repeat(v).take(usize::from(n)));
+                }
+                Op::Remove(_, v) => {
+                    let expected = model.iter().position(|&m| m == v);
+                    assert_eq!(purse.remove(make(v)), expected.is_some());
+                    expected.map(|index| model.remove(index));
+                }
+                Op::RemoveAll(_, v) => {
+                    let before = model.len();
+                    model.retain(|&m| m != v);
+                    assert_eq!(purse.remove_all(&make(v)), (before - model.len()) as u64);
+                }
+                Op::Pop(_) => match purse.pop::<T>() {
+                    Some(popped) => {
+                        let index = model.iter().position(|&m| make(m) == popped);
+                        model.remove(index.expect("popped a value that was never inserted"));
+                    }
+                    None => assert!(model.is_empty()),
+                },
+                Op::TakeAll(_) => {
+                    assert_eq!(purse.take_all_of_type::<T>().len(), model.len());
+                    model.clear();
+                }
+                Op::RegisterHashed(_) => purse.register_hashed::<T>(),
+                Op::RegisterOrdered(_) => purse.register_ordered::<T>(),
+                Op::RegisterList(_) => purse.register(crate::Strategy::<T>::list()),
+                Op::RegisterKeyed(_) => purse.register(crate::Strategy::<T>::keyed()),
+                Op::Clear => unreachable!(),
+            }
+        }
+
+        fn check<T: Any + Ord + fmt::Debug>(purse: &Purse, model: &[u8], make: fn(u8) -> T) {
+            assert_eq!(purse.count::<T>(), model.len() as u64);
+            assert_eq!(purse.iter_of_type::<T>().len(), model.len());
+            for v in 0..4u8 {
+                let expected = model.iter().filter(|&&m| m == v).count() as u64;
+                assert_eq!(purse.count_of(&make(v)), expected);
+                let below = model.iter().filter(|&&m| m < v).count() as u64;
+                assert_eq!(purse.rank(&make(v)), below);
+            }
+
+            let mut sorted = model.to_vec();
+            sorted.sort_unstable();
+            assert_eq!(purse.min::<T>(), sorted.first().map(|&m| make(m)).as_ref());
+            assert_eq!(purse.max::<T>(), sorted.last().map(|&m| make(m)).as_ref());
+            for (n, &m) in sorted.iter().enumerate() {
+                assert_eq!(purse.nth_smallest::<T>(n as u64), Some(&make(m)));
+            }
+            assert_eq!(purse.nth_smallest::<T>(sorted.len() as u64), None);
+            let in_range: Vec<T> = sorted
+                .iter()
+                .filter(|&&m| (1..3).contains(&m))
+                .map(|&m| make(m))
+                .collect();
+            let range = purse.range(make(1)..make(3));
+            assert_eq!(range.len(), in_range.len());
+            assert!(range.eq(in_range.iter()));
+        }
+
+        proptest! {
+            #[test]
+            fn accounting_matches_model(
+                ops in prop::collection::vec(op(), 0..64),
+                insertion_ordered in any::<bool>(),
+            ) {
+                let mut purse = if insertion_ordered {
+                    Purse::insertion_ordered()
+                } else {
+                    Purse::new()
+                };
+                let mut model: HashMap<Kind, Vec<u8>> = HashMap::default();
+                let type_id = |kind: Kind| match kind {
+                    Kind::U8 => TypeId::of::<u8>(),
+                    Kind::U16 => TypeId::of::<u16>(),
+                    Kind::Str => TypeId::of::<String>(),
+                };
+
+                for op in &ops {
+                    let kind = match *op {
+                        Op::Insert(k, _)
+                        | Op::InsertN(k, _, _)
+                        | Op::Remove(k, _)
+                        | Op::RemoveAll(k, _)
+                        | Op::Pop(k)
+                        | Op::TakeAll(k)
+                        | Op::RegisterHashed(k)
+                        | Op::RegisterOrdered(k)
+                        | Op::RegisterList(k)
+                        | Op::RegisterKeyed(k) => k,
+                        Op::Clear => {
+                            purse.clear();
+                            model.clear();
+                            continue;
+                        }
+                    };
+                    let values = model.entry(kind).or_default();
+                    match kind {
+                        Kind::U8 => apply(&mut purse, values, op, |v| v),
+                        Kind::U16 => apply(&mut purse, values, op, u16::from),
+                        Kind::Str => apply(&mut purse, values, op, |v| v.to_string()),
+                    }
+
+                    let empty = Vec::new();
+                    let values = |kind| model.get(&kind).unwrap_or(&empty);
+                    check(&purse, values(Kind::U8), |v| v);
+                    check(&purse, values(Kind::U16), u16::from);
+                    check(&purse, values(Kind::Str), |v| v.to_string());
+
+                    let total: usize = model.values().map(Vec::len).sum();
+                    let mut types: Vec<TypeId> = model
+                        .iter()
+                        .filter(|(_, values)| !values.is_empty())
+                        .map(|(&kind, _)| type_id(kind))
+                        .collect();
+                    types.sort();
+                    let mut purse_types = purse.types();
+                    purse_types.sort();
+
+                    prop_assert_eq!(purse.len(), total);
+                    prop_assert_eq!(purse.is_empty(), total == 0);
+                    prop_assert_eq!(purse.len_types(), types.len());
+                    prop_assert_eq!(&purse_types, &types);
+                    prop_assert_eq!(purse.iter().count(), total);
+
+                    let by_frequency = purse.types_by_frequency();
+                    prop_assert_eq!(by_frequency.len(), types.len());
+                    prop_assert!(by_frequency.windows(2).all(|w| w[0].1 >= w[1].1));
+                    for (kind, values) in &model {
+                        if !values.is_empty() {
+                            prop_assert!(by_frequency
+                                .contains(&(type_id(*kind), values.len() as u64)));
+                        }
+                    }
+                    let max = model.values().map(Vec::len).max().unwrap_or(0) as u64;
+                    match purse.most_common_type() {
+                        Some(most_common) => prop_assert!(by_frequency.contains(&(most_common, max))),
+                        None => prop_assert_eq!(total, 0),
+                    }
+                }
+            }
+        }
+    }
 }
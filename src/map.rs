@@ -0,0 +1,46 @@
+//! The hash maps and sets the crate is built on.
+//!
+//! These are hashbrown's, which only need `alloc`, with the default hasher
+//! swapped for std's `RandomState` when the `std` feature is enabled so that
+//! values are hashed the way std's own collections would hash them.
+
+use core::hash::Hasher;
+use core::mem;
+
+pub(crate) use hashbrown::hash_map;
+
+#[cfg(feature = "std")]
+pub(crate) type DefaultState = std::collections::hash_map::RandomState;
+#[cfg(not(feature = "std"))]
+pub(crate) type DefaultState = hashbrown::DefaultHashBuilder;
+
+pub(crate) type HashMap<K, V, S = DefaultState> = hashbrown::HashMap<K, V, S>;
+pub(crate) type HashSet<T, S = DefaultState> = hashbrown::HashSet<T, S>;
+
+/// Returns a hasher that hashes equal values the same way in every purse, on
+/// every call and with or without the `std` feature, for hashing the contents
+/// of a purse as a whole.
+///
+/// This is SipHash-2-4 with fixed keys, which core provides on every target.
+/// std's `DefaultHasher` is left alone, as its algorithm may change between
+/// releases.
+#[allow(deprecated)]
+pub(crate) fn fixed_hasher() -> impl Hasher {
+    core::hash::SipHasher::new_with_keys(0x7075_7273_655f_6b30, 0x7075_7273_655f_6b31)
+}
+
+/// Estimates the bytes allocated by a table holding up to `capacity` entries of
+/// type `T`, which is what the `capacity` method of a map or set reports.
+///
+/// Besides the entries, the table keeps a control byte per bucket and a group
+/// of trailing control bytes, and has more buckets than its capacity so that
+/// it never fills up completely.
+pub(crate) fn table_size<T>(capacity: usize) -> usize {
+    const GROUP_WIDTH: usize = 16;
+    let buckets = match capacity {
+        0 => return 0,
+        1..=7 => capacity + 1,
+        _ => capacity / 7 * 8,
+    };
+    buckets * (mem::size_of::<T>() + 1) + GROUP_WIDTH
+}
@@ -0,0 +1,156 @@
+//! Allocation control and memory accounting for a [`Purse`].
+//!
+//! Each type's [`Column`](crate::Column) reports the room it has set aside
+//! and the bytes it has allocated, and the purse adds the boxes the column
+//! lives in and its share of the per-type maps. Sizes of hash tables are
+//! estimated from their capacity, since the maps don't report their
+//! allocations.
+
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::hash::BuildHasher;
+use core::mem;
+
+use crate::map::table_size;
+use crate::vtable::VTable;
+use crate::{Bucket, Purse};
+
+/// The memory held by a purse, as reported by [`Purse::memory_usage`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The memory held for each type with storage in the purse, in no
+    /// particular order.
+    pub types: Vec<TypeMemory>,
+    /// Bytes allocated by the maps from each type to its storage, registered
+    /// strategy and recorded trait impls, including room for types that aren't
+    /// there yet.
+    pub maps: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the total number of bytes held by the purse.
+    pub fn total(&self) -> usize {
+        let types: usize = self.types.iter().map(|t| t.values + t.boxes).sum();
+        types + self.maps
+    }
+    /// Returns the memory held for type `T`, if the purse has storage for it.
+    pub fn of<T: Any>(&self) -> Option<&TypeMemory> {
+        let type_id = TypeId::of::<T>();
+        self.types.iter().find(|t| t.type_id == type_id)
+    }
+}
+
+/// The memory held for one type in a purse, see [`MemoryUsage`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypeMemory {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    /// The number of elements of the type.
+    pub len: usize,
+    /// Bytes allocated by the type's [`Column`](crate::Column) for its elements,
+    /// including room for elements it doesn't hold yet and bookkeeping such as
+    /// counts and keys. See [`Column::heap_size`](crate::Column::heap_size).
+    pub values: usize,
+    /// Bytes of the boxes the column is kept in.
+    pub boxes: usize,
+    /// Bytes of the type's entries in the purse's maps, which are part of
+    /// [`MemoryUsage::maps`].
+    pub map_entries: usize,
+}
+
+impl<S: BuildHasher> Purse<S> {
+    /// Makes room for at least `additional` more elements of type `T`, creating the
+    /// type's storage if needed.
+    ///
+    /// Storage that keeps equal elements once, such as [`Strategy::hashed`](crate::Strategy::hashed),
+    /// makes room for `additional` distinct values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.reserve::<u32>(100);
+    /// assert!(purse.capacity::<u32>() >= 100);
+    /// assert!(purse.is_empty());
+    /// ```
+    pub fn reserve<T: Any>(&mut self, additional: usize) {
+        self.column_or_default::<T>().reserve(additional);
+    }
+    /// Returns how many elements of type `T` the purse can hold without allocating
+    /// again, or 0 if it has no storage for the type. See
+    /// [`Column::capacity`](crate::Column::capacity).
+    pub fn capacity<T: Any>(&self) -> usize {
+        self.column::<T>().map_or(0, |column| column.capacity())
+    }
+    /// Frees the memory the purse holds beyond its elements.
+    ///
+    /// This drops the storage of types without elements, which the purse creates
+    /// again on the next insertion, and shrinks the storage of the others and the
+    /// purse's own maps. Registered strategies are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.reserve::<u32>(100);
+    /// purse.insert(1u32);
+    /// purse.reserve::<char>(100);
+    ///
+    /// purse.shrink_to_fit();
+    /// assert_eq!(purse.capacity::<u32>(), 1);
+    /// assert_eq!(purse.capacity::<char>(), 0);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.data.retain(|_, bucket| bucket.len() > 0);
+        for bucket in self.data.values_mut() {
+            bucket.0.shrink_to_fit();
+        }
+        self.data.shrink_to_fit();
+        self.factories.shrink_to_fit();
+        self.vtables.shrink_to_fit();
+    }
+    /// Reports the memory held by the purse, broken down by type.
+    ///
+    /// Memory owned by the elements themselves, such as the contents of a `String`,
+    /// isn't counted, and the sizes of hash tables are estimated from their capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.reserve::<u64>(10);
+    /// purse.insert(1u64);
+    ///
+    /// let usage = purse.memory_usage();
+    /// let u64s = usage.of::<u64>().unwrap();
+    /// assert_eq!(u64s.len, 1);
+    /// assert!(u64s.values >= 10 * 8);
+    /// assert!(usage.total() > u64s.values + u64s.boxes);
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage {
+        // Besides the entry itself, the table keeps a control byte per entry.
+        let entry = |size: usize| size + 1;
+        let types = self
+            .data
+            .iter()
+            .map(|(type_id, bucket)| {
+                let mut usage = bucket.0.memory_usage();
+                usage.map_entries = entry(mem::size_of::<(TypeId, Bucket)>());
+                if self.factories.contains_key(type_id) {
+                    usage.map_entries += entry(mem::size_of::<(TypeId, fn() -> Bucket)>());
+                }
+                if self.vtables.contains_key(type_id) {
+                    usage.map_entries += entry(mem::size_of::<(TypeId, VTable)>());
+                }
+                usage
+            })
+            .collect();
+        let maps = table_size::<(TypeId, Bucket)>(self.data.capacity())
+            + table_size::<(TypeId, fn() -> Bucket)>(self.factories.capacity())
+            + table_size::<(TypeId, VTable)>(self.vtables.capacity());
+        MemoryUsage { types, maps }
+    }
+}
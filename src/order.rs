@@ -0,0 +1,163 @@
+//! Order statistics over the items of one type in a [`Purse`].
+//!
+//! Each method dispatches to the type's [`Column`], so that types registered
+//! with [`Purse::register_ordered`] answer from their sorted storage and other
+//! types fall back to looking at every item.
+
+use alloc::vec::Vec;
+use core::any::Any;
+use core::hash::BuildHasher;
+use core::ops::{Bound, RangeBounds};
+
+use crate::{Column, Purse, RangeIter};
+
+/// Checks for ranges that `BTreeMap::range` rejects, which contain no values.
+fn is_inverted<T: Ord, R: RangeBounds<T>>(range: &R) -> bool {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) => start > end,
+        _ => false,
+    }
+}
+
+impl<S: BuildHasher> Purse<S> {
+    /// Returns the smallest element of a specific type.
+    ///
+    /// This takes logarithmic time for types registered with
+    /// [`register_ordered`](Purse::register_ordered), and scans every element of the type
+    /// otherwise.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element. This type must implement `Any` and `Ord`.
+    ///
+    /// # Returns
+    /// `Some(elem)` if the purse holds an element of type `T`, otherwise `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.register_ordered::<i32>();
+    /// purse.insert(3);
+    /// purse.insert(-1);
+    /// purse.insert(7);
+    /// assert_eq!(purse.min::<i32>(), Some(&-1));
+    /// assert_eq!(purse.min::<u8>(), None);
+    /// ```
+    pub fn min<T: Any + Ord>(&self) -> Option<&T> {
+        self.column::<T>().and_then(Column::min)
+    }
+    /// Returns the largest element of a specific type.
+    ///
+    /// This takes logarithmic time for types registered with
+    /// [`register_ordered`](Purse::register_ordered), and scans every element of the type
+    /// otherwise.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the element. This type must implement `Any` and `Ord`.
+    ///
+    /// # Returns
+    /// `Some(elem)` if the purse holds an element of type `T`, otherwise `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert("pear");
+    /// purse.insert("apple");
+    /// assert_eq!(purse.max::<&str>(), Some(&"pear"));
+    /// ```
+    pub fn max<T: Any + Ord>(&self) -> Option<&T> {
+        self.column::<T>().and_then(Column::max)
+    }
+    /// Iterates in ascending order over the elements of a specific type that fall within
+    /// `range`.
+    ///
+    /// For types registered with [`register_ordered`](Purse::register_ordered), the iterator
+    /// is created in logarithmic time, plus time proportional to the number of distinct values
+    /// in the range. Otherwise, the matching elements are collected and sorted up front. A
+    /// range whose start lies after its end is empty.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the elements. This type must implement `Any` and `Ord`.
+    /// - `R`: The type of the range, such as `a..b` or `a..=b`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.register_ordered::<u32>();
+    /// for n in [5u32, 1, 9, 5, 3] {
+    ///     purse.insert(n);
+    /// }
+    /// let mid: Vec<_> = purse.range(3u32..9).copied().collect();
+    /// assert_eq!(mid, vec![3, 5, 5]);
+    /// assert_eq!(purse.range(6u32..).len(), 1);
+    /// ```
+    pub fn range<T: Any + Ord, R: RangeBounds<T>>(&self, range: R) -> RangeIter<'_, T> {
+        match self.column::<T>() {
+            Some(column) if !is_inverted(&range) => {
+                column.range((range.start_bound(), range.end_bound()))
+            }
+            _ => RangeIter::from_sorted(Vec::new()),
+        }
+    }
+    /// Counts the elements of a specific type that are strictly less than `value`.
+    ///
+    /// This is the position `value` would take among the elements of its type in ascending
+    /// order, so `purse.nth_smallest(purse.rank(&x))` is `Some(&x)` whenever `x` is in the
+    /// purse.
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the elements. This type must implement `Any` and `Ord`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.register_ordered::<u8>();
+    /// purse.insert_n(1u8, 3);
+    /// purse.insert(4u8);
+    /// assert_eq!(purse.rank(&1u8), 0);
+    /// assert_eq!(purse.rank(&2u8), 3);
+    /// assert_eq!(purse.rank(&9u8), 4);
+    /// ```
+    pub fn rank<T: Any + Ord>(&self, value: &T) -> u64 {
+        self.column::<T>().map_or(0, |column| column.rank(value))
+    }
+    /// Returns the element of a specific type at position `n` in ascending order, counting
+    /// from zero.
+    ///
+    /// Duplicates each take up a position, so `nth_smallest(0)` is the same as
+    /// [`min`](Purse::min) and `nth_smallest(count - 1)` the same as [`max`](Purse::max).
+    ///
+    /// # Type Parameters
+    /// - `T`: The type of the elements. This type must implement `Any` and `Ord`.
+    ///
+    /// # Returns
+    /// `Some(elem)` if the purse holds more than `n` elements of type `T`, otherwise `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Purse;
+    /// let mut purse = Purse::new();
+    /// purse.insert(30);
+    /// purse.insert(10);
+    /// purse.insert(20);
+    /// purse.insert(10);
+    /// assert_eq!(purse.nth_smallest::<i32>(1), Some(&10));
+    /// assert_eq!(purse.nth_smallest::<i32>(2), Some(&20));
+    /// assert_eq!(purse.nth_smallest::<i32>(4), None);
+    /// ```
+    pub fn nth_smallest<T: Any + Ord>(&self, n: u64) -> Option<&T> {
+        self.column::<T>().and_then(|column| column.nth_smallest(n))
+    }
+}
@@ -0,0 +1,168 @@
+//! Limits on how much a [`Purse`] holds.
+//!
+//! A [`Quota`] is checked by [`Purse::try_insert`] and
+//! [`Purse::try_insert_n`], against the number of elements in the purse and
+//! the bytes they take up, and an insertion that would go over a limit is
+//! reported as an error. Plain [`Purse::insert`] doesn't check the quota.
+
+use core::any::{type_name, Any, TypeId};
+use core::fmt;
+use core::hash::BuildHasher;
+use core::mem;
+
+use crate::{Bucket, Purse, PurseError};
+
+/// Limits on the contents of a [`Purse`], set with [`Purse::set_quota`].
+///
+/// Every limit is off by default. Limits are checked before inserting through
+/// [`Purse::try_insert`] and [`Purse::try_insert_n`], and count every inserted
+/// element, including duplicates that storage such as
+/// [`Strategy::set`](crate::Strategy::set) would then ignore.
+///
+/// The limit of a type looks at that type's column alone, while
+/// [`max_len`](Quota::max_len) and [`max_bytes`](Quota::max_bytes) add up every
+/// column, so each checked insertion takes time proportional to the number of
+/// types in the purse while either is set.
+///
+/// # Examples
+///
+/// ```
+/// # use purse::{Purse, Quota};
+/// let mut purse = Purse::new();
+/// purse.set_quota(Quota {
+///     max_len: Some(1_000),
+///     max_per_type: Some(100),
+///     ..Quota::default()
+/// });
+/// assert_eq!(purse.quota().max_len, Some(1_000));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Quota {
+    /// The most elements the purse may hold, across all types.
+    pub max_len: Option<usize>,
+    /// The most elements the purse may hold of each type.
+    pub max_per_type: Option<usize>,
+    /// The most bytes the elements may take up, counting `size_of` bytes for
+    /// each.
+    ///
+    /// Room the columns set aside, their bookkeeping and memory owned by the
+    /// elements, such as the contents of a `String`, don't count against the
+    /// budget. See [`Purse::memory_usage`] for those.
+    pub max_bytes: Option<usize>,
+}
+
+/// A limit of a [`Quota`], reported by [`PurseError::QuotaExceeded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// [`Quota::max_len`] elements.
+    Len(usize),
+    /// [`Quota::max_per_type`] elements of the type.
+    PerType(usize),
+    /// [`Quota::max_bytes`] bytes.
+    Bytes(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Len(n) => write!(f, "{n} elements"),
+            Limit::PerType(n) => write!(f, "{n} elements per type"),
+            Limit::Bytes(n) => write!(f, "{n} bytes"),
+        }
+    }
+}
+
+impl<S: BuildHasher> Purse<S> {
+    /// Returns the limits on the contents of the purse.
+    pub fn quota(&self) -> Quota {
+        self.quota
+    }
+    /// Sets the limits on the contents of the purse, which apply from the next
+    /// insertion.
+    ///
+    /// Elements already in the purse are kept, even if they exceed the new limits.
+    pub fn set_quota(&mut self, quota: Quota) {
+        self.quota = quota;
+    }
+    /// Inserts an element into the purse, unless that would exceed its
+    /// [`Quota`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PurseError::QuotaExceeded`] with the first limit the insertion would
+    /// exceed, and drops the element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::{Limit, Purse, PurseError, Quota};
+    /// let mut purse = Purse::new();
+    /// purse.set_quota(Quota {
+    ///     max_per_type: Some(2),
+    ///     ..Quota::default()
+    /// });
+    /// assert!(purse.try_insert(1u8).is_ok());
+    /// assert!(purse.try_insert(2u8).is_ok());
+    /// assert_eq!(
+    ///     purse.try_insert(3u8),
+    ///     Err(PurseError::QuotaExceeded {
+    ///         type_name: "u8",
+    ///         limit: Limit::PerType(2),
+    ///     })
+    /// );
+    /// assert!(purse.try_insert('a').is_ok());
+    /// ```
+    pub fn try_insert<T: Any>(&mut self, elem: T) -> Result<(), PurseError> {
+        self.check_quota::<T>(1)?;
+        self.column_or_default::<T>().insert(elem);
+        Ok(())
+    }
+    /// Inserts `n` copies of an element into the purse, unless that would exceed its
+    /// [`Quota`]. See [`insert_n`](Purse::insert_n).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PurseError::QuotaExceeded`] if the copies don't all fit, in which case
+    /// none are inserted. Inserting no copies always succeeds, even in a purse that
+    /// already exceeds its quota.
+    pub fn try_insert_n<T: Any + Clone>(&mut self, elem: T, n: u64) -> Result<(), PurseError> {
+        if n == 0 {
+            return Ok(());
+        }
+        self.check_quota::<T>(n)?;
+        self.column_or_default::<T>().insert_n(elem, n);
+        Ok(())
+    }
+    /// Checks that `n` more elements of type `T` fit within the quota.
+    pub(crate) fn check_quota<T: Any>(&self, n: u64) -> Result<(), PurseError> {
+        match self.exceeded_limit::<T>(&self.quota, n) {
+            Some(limit) => Err(PurseError::QuotaExceeded {
+                type_name: type_name::<T>(),
+                limit,
+            }),
+            None => Ok(()),
+        }
+    }
+    /// Returns the first limit of `quota` that `n` more elements of type `T` would
+    /// exceed, checking the limit of the type first.
+    pub(crate) fn exceeded_limit<T: Any>(&self, quota: &Quota, n: u64) -> Option<Limit> {
+        let n = usize::try_from(n).unwrap_or(usize::MAX);
+        if let Some(max) = quota.max_per_type {
+            if self.len_of(&TypeId::of::<T>()).saturating_add(n) > max {
+                return Some(Limit::PerType(max));
+            }
+        }
+        if let Some(max) = quota.max_len {
+            if self.len().saturating_add(n) > max {
+                return Some(Limit::Len(max));
+            }
+        }
+        if let Some(max) = quota.max_bytes {
+            let used: usize = self.data.values().map(Bucket::len_bytes).sum();
+            if used.saturating_add(n.saturating_mul(mem::size_of::<T>())) > max {
+                return Some(Limit::Bytes(max));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,133 @@
+//! Storage for the types of an insertion-ordered purse, see
+//! [`Purse::insertion_ordered`](crate::Purse::insertion_ordered).
+//!
+//! A [`SequencedColumn`] is a list that also records a sequence number for
+//! each item, taken from a process-wide counter when the item is stored. The
+//! numbers only ever grow, so the purse can interleave the items of all its
+//! types in the order they were inserted by merging on them. Keyed columns of
+//! such a purse draw their numbers from the same counter.
+
+use alloc::vec::Vec;
+use core::any::Any;
+use core::mem;
+
+use crate::counter::Counter;
+use crate::keyed::KeyedColumn;
+use crate::{Column, TypeIter};
+
+pub(crate) static NEXT_SEQUENCE: Counter = Counter::new();
+
+/// Keeps every item in a `Vec<T>` in insertion order, along with its sequence
+/// number.
+pub(crate) struct SequencedColumn<T> {
+    items: Vec<T>,
+    /// The sequence number of each item in `items`, in ascending order.
+    sequence: Vec<u64>,
+}
+
+impl<T> Default for SequencedColumn<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            sequence: Vec::new(),
+        }
+    }
+}
+
+impl<T> SequencedColumn<T> {
+    pub(crate) fn sequence(&self) -> &[u64] {
+        &self.sequence
+    }
+}
+
+/// Stores `elems` in `column`, keeping the sequence number each comes with if the
+/// column records them.
+///
+/// Used to move the items of a type into the column of a new strategy without
+/// moving them to the end of the purse's order.
+pub(crate) fn insert_sequenced<T: Any>(column: &mut dyn Column<T>, mut elems: Vec<(u64, T)>) {
+    elems.sort_by_key(|&(sequence, _)| sequence);
+    if let Some(any) = column.as_any_mut() {
+        if let Some(list) = any.downcast_mut::<SequencedColumn<T>>() {
+            let (sequence, items): (Vec<_>, Vec<_>) = elems.into_iter().unzip();
+            list.sequence.extend(sequence);
+            list.items.extend(items);
+            return;
+        }
+        if let Some(keyed) = any.downcast_mut::<KeyedColumn<T>>() {
+            for (sequence, elem) in elems {
+                keyed.insert_sequenced(elem, sequence);
+            }
+            return;
+        }
+    }
+    elems.into_iter().for_each(|(_, elem)| column.insert(elem));
+}
+
+impl<T: Any> Column<T> for SequencedColumn<T> {
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+    fn insert(&mut self, value: T) {
+        self.items.push(value);
+        self.sequence.push(NEXT_SEQUENCE.next());
+    }
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::from_slice(&self.items)
+    }
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        let mut kept = Vec::with_capacity(self.items.len());
+        self.items.retain(|elem| {
+            let remove = removed < limit && matches(elem);
+            removed += u64::from(remove);
+            kept.push(!remove);
+            !remove
+        });
+        let mut kept = kept.into_iter();
+        self.sequence.retain(|_| kept.next().unwrap_or(true));
+        removed
+    }
+    /// Removes the most recently inserted item.
+    fn pop(&mut self) -> Option<T> {
+        self.sequence.pop();
+        self.items.pop()
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        self.sequence.clear();
+        core::mem::take(&mut self.items)
+    }
+    fn contains(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.items.contains(value)
+    }
+    fn as_slice(&self) -> Option<&[T]> {
+        Some(&self.items)
+    }
+    fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        Some(&mut self.items)
+    }
+    fn capacity(&self) -> usize {
+        self.items.capacity().min(self.sequence.capacity())
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional);
+        self.sequence.reserve(additional);
+    }
+    fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit();
+        self.sequence.shrink_to_fit();
+    }
+    fn heap_size(&self) -> usize {
+        self.items.capacity() * mem::size_of::<T>()
+            + self.sequence.capacity() * mem::size_of::<u64>()
+    }
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
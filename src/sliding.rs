@@ -0,0 +1,238 @@
+//! A purse that only holds the elements of a recent window.
+//!
+//! A [`SlidingPurse`] stores every element under an [`ItemHandle`], and queues
+//! the handles in order of insertion along with the time of insertion. The
+//! window only ever drops its oldest elements, so they are always at the
+//! front of the queue, and dropping one is a single removal by handle.
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use core::any::Any;
+use core::fmt;
+use core::ops::Deref;
+use core::time::Duration;
+
+use crate::clock::Clock;
+#[cfg(feature = "std")]
+use crate::clock::SystemClock;
+use crate::handle::ErasedHandle;
+use crate::map::HashSet;
+use crate::Purse;
+
+/// The elements a [`SlidingPurse`] holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Window {
+    /// The elements of the last `n` insertions.
+    Inserts(usize),
+    /// The elements inserted less than the given time ago.
+    Time(Duration),
+}
+
+/// An element in the window.
+struct Entry {
+    /// The time of insertion.
+    at: Duration,
+    handle: ErasedHandle,
+}
+
+/// A purse that holds the elements of the last insertions, or of the last stretch of
+/// time measured by a [`Clock`], dropping elements once they leave the window.
+///
+/// Insertions drop the elements that left the window. Everything that reads the purse is
+/// available through `Deref`, but only the methods listed below know about the window.
+///
+/// # Hidden elements
+///
+/// Under a [`Window::Time`] elements also leave as time passes, and stay in the purse until
+/// the next insertion or [`prune`](SlidingPurse::prune) drops them. Until then, exactly
+/// these methods skip them:
+///
+/// - [`len`](SlidingPurse::len) and [`is_empty`](SlidingPurse::is_empty)
+/// - [`count`](SlidingPurse::count), [`count_of`](SlidingPurse::count_of) and
+///   [`contains`](SlidingPurse::contains)
+/// - [`iter`](SlidingPurse::iter)
+///
+/// Every other read goes through `Deref` to the [`Purse`] and still sees them, for example
+/// [`Purse::iter_of_type`], [`Purse::get_all_of_type`], [`Purse::min`] and
+/// [`Purse::max`], the `Debug` output, and the methods above when called as [`Purse`]
+/// methods, such as `Purse::len(&purse)`. Call [`prune`](SlidingPurse::prune) first to have
+/// those reads skip them too.
+///
+/// # Examples
+///
+/// ```
+/// # use core::time::Duration;
+/// # use purse::{ManualClock, SlidingPurse, Window};
+/// let clock = ManualClock::new();
+/// let mut requests = SlidingPurse::with_clock(Window::Time(Duration::from_secs(60)), &clock);
+/// requests.insert("GET");
+/// clock.advance(Duration::from_secs(30));
+/// requests.insert("POST");
+/// requests.insert("GET");
+/// assert_eq!(requests.count_of(&"GET"), 2);
+///
+/// // The first request is now a minute old.
+/// clock.advance(Duration::from_secs(30));
+/// assert_eq!(requests.count_of(&"GET"), 1);
+/// assert_eq!(requests.count::<&str>(), 2);
+/// ```
+pub struct SlidingPurse<C> {
+    purse: Purse,
+    window: Window,
+    clock: C,
+    /// The elements in order of insertion.
+    entries: VecDeque<Entry>,
+}
+
+#[cfg(feature = "std")]
+impl SlidingPurse<SystemClock> {
+    /// Creates an empty purse that holds the elements of `window`, measuring time with
+    /// a [`SystemClock`].
+    pub fn new(window: Window) -> Self {
+        Self::with_clock(window, SystemClock::new())
+    }
+}
+
+impl<C: Clock> SlidingPurse<C> {
+    /// Creates an empty purse that holds the elements of `window`, measuring time with
+    /// `clock`.
+    pub fn with_clock(window: Window, clock: C) -> Self {
+        Self {
+            purse: Purse::new(),
+            window,
+            clock,
+            entries: VecDeque::new(),
+        }
+    }
+    /// Returns the window of the purse.
+    pub fn window(&self) -> Window {
+        self.window
+    }
+    /// Changes the window of the purse, dropping the elements that are outside of it and
+    /// returning how many there were.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::{ManualClock, SlidingPurse, Window};
+    /// let mut purse = SlidingPurse::with_clock(Window::Inserts(10), ManualClock::new());
+    /// (0..10u32).for_each(|i| purse.insert(i));
+    /// assert_eq!(purse.set_window(Window::Inserts(3)), 7);
+    /// assert_eq!(purse.get_all_of_type::<u32>(), [&7, &8, &9]);
+    /// ```
+    pub fn set_window(&mut self, window: Window) -> usize {
+        self.window = window;
+        self.prune()
+    }
+    /// Returns the clock of the purse.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+    /// Inserts an element, then drops the elements that left the window.
+    pub fn insert<T: Any>(&mut self, elem: T) {
+        let at = self.clock.now();
+        let handle = self.purse.insert_with_handle(elem);
+        self.entries.push_back(Entry {
+            at,
+            handle: ErasedHandle::new(handle),
+        });
+        self.prune_at(at);
+    }
+    /// Drops the elements that left the window, returning how many there were.
+    pub fn prune(&mut self) -> usize {
+        self.prune_at(self.clock.now())
+    }
+    /// Returns the number of elements in the window.
+    pub fn len(&self) -> usize {
+        self.purse.len() - self.stale().count()
+    }
+    /// Returns `true` if the window has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Counts the elements of type `T` in the window, see [`Purse::count`].
+    pub fn count<T: Any>(&self) -> u64 {
+        let stale = self.stale_of::<T>().count();
+        self.purse.count::<T>() - stale as u64
+    }
+    /// Counts the elements in the window equal to `elem`, see [`Purse::count_of`].
+    pub fn count_of<T: Any + Eq>(&self, elem: &T) -> u64 {
+        let stale = self.stale_of::<T>().filter(|e| *e == elem).count();
+        self.purse.count_of(elem) - stale as u64
+    }
+    /// Checks if the window has an element equal to `t`, see [`Purse::contains`].
+    pub fn contains<T: Any + Eq>(&self, t: T) -> bool {
+        self.count_of(&t) > 0
+    }
+    /// Iterates over the elements in the window, see [`Purse::iter`].
+    pub fn iter(&self) -> Box<dyn Iterator<Item = &dyn Any> + '_> {
+        let stale: HashSet<u64> = self.stale_entries().map(|entry| entry.handle.key).collect();
+        if stale.is_empty() {
+            return self.purse.iter();
+        }
+        self.purse.iter_without(stale)
+    }
+    /// Removes all elements.
+    pub fn clear(&mut self) {
+        self.purse.clear();
+        self.entries.clear();
+    }
+    /// Unwraps the underlying purse, which keeps the elements that left the window
+    /// since they were last dropped.
+    pub fn into_inner(self) -> Purse {
+        self.purse
+    }
+    /// Returns whether the element at `position` in `entries` is outside the window.
+    fn has_left(&self, position: usize, entry: &Entry, now: Duration) -> bool {
+        match self.window {
+            Window::Inserts(n) => self.entries.len() - position > n,
+            Window::Time(window) => now.saturating_sub(entry.at) >= window,
+        }
+    }
+    fn prune_at(&mut self, now: Duration) -> usize {
+        let mut dropped = 0;
+        while let Some(entry) = self.entries.front() {
+            if !self.has_left(0, entry, now) {
+                break;
+            }
+            let entry = self.entries.pop_front().expect("the queue has a front");
+            entry.handle.remove(&mut self.purse);
+            dropped += 1;
+        }
+        dropped
+    }
+    /// Returns the entries of the elements that left the window but weren't dropped yet.
+    fn stale_entries(&self) -> impl Iterator<Item = &Entry> + '_ {
+        let now = self.clock.now();
+        self.entries
+            .iter()
+            .enumerate()
+            .take_while(move |(position, entry)| self.has_left(*position, entry, now))
+            .map(|(_, entry)| entry)
+    }
+    /// Returns the elements that left the window but weren't dropped yet.
+    fn stale(&self) -> impl Iterator<Item = &dyn Any> + '_ {
+        self.stale_entries()
+            .filter_map(|entry| entry.handle.get(&self.purse))
+    }
+    fn stale_of<T: Any>(&self) -> impl Iterator<Item = &T> + '_ {
+        self.stale().filter_map(|elem| elem.downcast_ref())
+    }
+}
+
+impl<C> Deref for SlidingPurse<C> {
+    type Target = Purse;
+
+    fn deref(&self) -> &Purse {
+        &self.purse
+    }
+}
+
+impl<C> fmt::Debug for SlidingPurse<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlidingPurse")
+            .field("purse", &self.purse)
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
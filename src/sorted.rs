@@ -0,0 +1,212 @@
+//! Ordered storage for types that opt into it via
+//! [`Purse::register_ordered`](crate::Purse::register_ordered).
+//!
+//! A [`SortedBag`] works like a [`HashedBag`](crate::hashed::HashedBag), but
+//! keeps its distinct values in a `BTreeMap`, so items are iterated in
+//! ascending order and order statistics such as the minimum, a range of values
+//! or the rank of a value don't require sorting the type's items first. The
+//! counts aren't summed up the tree, so ranks and positions still take a walk
+//! over the distinct values before them.
+
+use alloc::collections::{btree_map, BTreeMap};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::borrow::Borrow;
+use core::mem;
+use core::ops::{Bound, RangeBounds};
+
+use crate::counted::{self, repeat_owned};
+use crate::{Column, RangeIter, TypeIter};
+
+/// Iterator over the items of a [`SortedBag`], in ascending order.
+pub(crate) type Iter<'a, T> = counted::Iter<'a, T, btree_map::Iter<'a, T, u64>>;
+
+/// Iterator over the items of a [`SortedBag`] within a range, in ascending order.
+pub(crate) type Range<'a, T> = counted::Iter<'a, T, btree_map::Range<'a, T, u64>>;
+
+/// Stores each distinct value of `T` once, in order, alongside the number of
+/// times it was inserted.
+#[derive(Clone)]
+pub(crate) struct SortedBag<T> {
+    counts: BTreeMap<T, u64>,
+    len: u64,
+}
+
+impl<T> Default for SortedBag<T> {
+    fn default() -> Self {
+        Self {
+            counts: BTreeMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> SortedBag<T> {
+    /// Iterates over every stored item in ascending order, yielding each
+    /// distinct value as many times as it was inserted.
+    pub(crate) fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self.counts.iter(), self.len)
+    }
+}
+
+impl<T: Ord> SortedBag<T> {
+    pub(crate) fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.counts.contains_key(value)
+    }
+    pub(crate) fn multiplicity<Q>(&self, value: &Q) -> u64
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.counts.get(value).copied().unwrap_or(0)
+    }
+    pub(crate) fn remove_n<Q>(&mut self, value: &Q, n: u64) -> u64
+    where
+        T: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some(count) = self.counts.get_mut(value) else {
+            return 0;
+        };
+        let removed = n.min(*count);
+        *count -= removed;
+        if *count == 0 {
+            self.counts.remove(value);
+        }
+        self.len -= removed;
+        removed
+    }
+    pub(crate) fn min(&self) -> Option<&T> {
+        self.counts.keys().next()
+    }
+    pub(crate) fn max(&self) -> Option<&T> {
+        self.counts.keys().next_back()
+    }
+    /// Iterates in ascending order over the items within `range`.
+    pub(crate) fn range<R: RangeBounds<T>>(&self, range: R) -> Range<'_, T> {
+        let entries = self.counts.range(range);
+        let len = entries.clone().map(|(_, &n)| n).sum();
+        Range::new(entries, len)
+    }
+    /// Number of items strictly less than `value`, adding up the multiplicity
+    /// of every smaller distinct value.
+    pub(crate) fn rank(&self, value: &T) -> u64 {
+        self.counts.range(..value).map(|(_, &n)| n).sum()
+    }
+    /// The item at position `n` in ascending order, counting duplicates, found
+    /// by walking the distinct values from the smallest.
+    pub(crate) fn nth(&self, mut n: u64) -> Option<&T> {
+        for (value, &count) in &self.counts {
+            if n < count {
+                return Some(value);
+            }
+            n -= count;
+        }
+        None
+    }
+}
+
+impl<T: Ord + Clone> SortedBag<T> {
+    pub(crate) fn insert_n(&mut self, value: T, n: u64) {
+        if n == 0 {
+            return;
+        }
+        *self.counts.entry(value).or_insert(0) += n;
+        self.len += n;
+    }
+    /// Removes and returns the largest item.
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let value = self.max()?.clone();
+        self.remove_n(&value, 1);
+        Some(value)
+    }
+    /// Removes every item, yielding them in ascending order.
+    pub(crate) fn into_values(self) -> impl Iterator<Item = T> {
+        self.counts
+            .into_iter()
+            .flat_map(|(value, n)| repeat_owned(value, n))
+    }
+}
+
+impl<T: Any + Ord + Clone> Column<T> for SortedBag<T> {
+    /// Saturates at `usize::MAX` on targets where it is below the `u64` count.
+    fn len(&self) -> usize {
+        usize::try_from(self.len).unwrap_or(usize::MAX)
+    }
+    fn insert(&mut self, value: T) {
+        self.insert_n(value, 1);
+    }
+    fn insert_n(&mut self, value: T, n: u64) {
+        SortedBag::insert_n(self, value, n);
+    }
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::sorted(self.iter())
+    }
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        self.counts.retain(|value, count| {
+            if removed < limit && matches(value) {
+                let n = (*count).min(limit - removed);
+                *count -= n;
+                removed += n;
+            }
+            *count > 0
+        });
+        self.len -= removed;
+        removed
+    }
+    /// Removes the largest item.
+    fn pop(&mut self) -> Option<T> {
+        SortedBag::pop(self)
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        let len = usize::try_from(self.len).expect("the items don't fit in memory");
+        let mut elems = Vec::with_capacity(len);
+        elems.extend(core::mem::take(self).into_values());
+        elems
+    }
+    fn contains(&self, value: &T) -> bool {
+        SortedBag::contains(self, value)
+    }
+    fn count_of(&self, value: &T) -> u64 {
+        self.multiplicity(value)
+    }
+    fn remove_n(&mut self, value: &T, n: u64) -> u64 {
+        SortedBag::remove_n(self, value, n)
+    }
+    fn min(&self) -> Option<&T> {
+        SortedBag::min(self)
+    }
+    fn max(&self) -> Option<&T> {
+        SortedBag::max(self)
+    }
+    fn range(&self, range: (Bound<&T>, Bound<&T>)) -> RangeIter<'_, T> {
+        RangeIter::sorted(SortedBag::range(self, range))
+    }
+    fn rank(&self, value: &T) -> u64 {
+        SortedBag::rank(self, value)
+    }
+    fn nth_smallest(&self, n: u64) -> Option<&T> {
+        self.nth(n)
+    }
+    /// Returns the number of distinct values. The tree allocates a node at a
+    /// time, so it has no room set aside to report.
+    fn capacity(&self) -> usize {
+        self.counts.len()
+    }
+    /// Estimates the bytes of the tree's entries, not counting the unused room
+    /// in its nodes or their links.
+    fn heap_size(&self) -> usize {
+        self.counts.len() * mem::size_of::<(T, u64)>()
+    }
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
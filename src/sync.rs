@@ -0,0 +1,801 @@
+//! Purses that can cross thread boundaries.
+//!
+//! A [`Purse`] keeps its columns behind trait objects that don't promise to be
+//! `Send` or `Sync`, since it accepts any `'static` type, including `Rc`s and
+//! `RefCell`s. [`SendPurse`] is a purse whose inserting methods only accept
+//! `Send + Sync` types, which makes the purse as a whole safe to send and
+//! share. [`ConcurrentPurse`] shards a purse by type behind locks, so that
+//! threads working on different types don't wait on each other, and
+//! [`Mailbox`] lets threads and tasks wait for items of a type to arrive. The
+//! latter two are built on std's locks and need the `std` feature.
+
+#[cfg(feature = "std")]
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::borrow::Borrow;
+use core::hash::Hash;
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use core::{
+    any::TypeId,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+#[cfg(feature = "std")]
+use std::sync::{
+    Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+#[cfg(feature = "std")]
+use crate::map::HashMap;
+#[cfg(feature = "std")]
+use crate::{Bucket, Column, TypeIter};
+use crate::{ErasedValue, Purse, PurseError, Quota, Strategy, TypeIterMut};
+
+/// A [`Purse`] that can be sent to and shared between threads.
+///
+/// Only `Send + Sync` types can be inserted, and only strategies for such types
+/// registered. Everything that reads the purse is available through `Deref`,
+/// and the methods that change it mirror the ones of [`Purse`].
+///
+/// # Examples
+///
+/// ```
+/// # use purse::SendPurse;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let mut purse = SendPurse::new();
+/// purse.insert(42);
+/// purse.insert("hello");
+///
+/// let purse = Arc::new(purse);
+/// let handle = {
+///     let purse = Arc::clone(&purse);
+///     thread::spawn(move || purse.contains(42))
+/// };
+/// assert!(handle.join().unwrap());
+/// assert_eq!(purse.len(), 2);
+/// ```
+///
+/// Types that can't be sent are rejected at compile time:
+///
+/// ```compile_fail
+/// # use purse::SendPurse;
+/// let mut purse = SendPurse::new();
+/// purse.insert(std::rc::Rc::new(1));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Hash)]
+pub struct SendPurse {
+    inner: Purse,
+}
+
+// SAFETY: A purse owns its items, its columns and plain function pointers, and
+// has no interior mutability. Every item inserted into `inner` and every column
+// created for it is `Send + Sync`: the methods below only accept such types,
+// built-in strategies store `T` in `Send + Sync` collections, and custom ones
+// are only registered if made with `Strategy::custom_send`, which requires
+// their column to be `Send + Sync`. Shared access only hands out
+// `&Purse`, which can't insert anything.
+unsafe impl Send for SendPurse {}
+unsafe impl Sync for SendPurse {}
+
+impl SendPurse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Chooses how the elements of type `T` are stored. See [`Purse::register`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `strategy` was made with [`Strategy::custom`], whose column may not be
+    /// `Send` or `Sync`. Use [`Strategy::custom_send`] instead.
+    pub fn register<T: Any + Send + Sync>(&mut self, strategy: Strategy<T>) {
+        assert!(
+            strategy.is_send_sync(),
+            "custom strategies for a `SendPurse` must be made with `Strategy::custom_send`",
+        );
+        self.inner.register(strategy);
+    }
+    /// Switches the storage for type `T` to a hash-indexed bag. See
+    /// [`Purse::register_hashed`].
+    pub fn register_hashed<T: Any + Send + Sync + Hash + Eq + Clone>(&mut self) {
+        self.inner.register_hashed::<T>();
+    }
+    /// Switches the storage for type `T` to an ordered bag. See
+    /// [`Purse::register_ordered`].
+    pub fn register_ordered<T: Any + Send + Sync + Ord + Clone>(&mut self) {
+        self.inner.register_ordered::<T>();
+    }
+    /// Inserts an element into the purse.
+    pub fn insert<T: Any + Send + Sync>(&mut self, elem: T) {
+        self.inner.insert(elem);
+    }
+    /// Inserts an element into the purse, recording how to clone, compare, hash and print it.
+    /// See [`Purse::insert_value`].
+    pub fn insert_value<T: ErasedValue + Send + Sync>(&mut self, elem: T) {
+        self.inner.insert_value(elem);
+    }
+    /// Inserts `n` copies of an element into the purse. See [`Purse::insert_n`].
+    pub fn insert_n<T: Any + Send + Sync + Clone>(&mut self, elem: T, n: u64) {
+        self.inner.insert_n(elem, n);
+    }
+    /// Inserts an element into the purse, unless that would exceed its quota. See
+    /// [`Purse::try_insert`].
+    pub fn try_insert<T: Any + Send + Sync>(&mut self, elem: T) -> Result<(), PurseError> {
+        self.inner.try_insert(elem)
+    }
+    /// Sets the limits on the contents of the purse. See [`Purse::set_quota`].
+    pub fn set_quota(&mut self, quota: Quota) {
+        self.inner.set_quota(quota);
+    }
+    /// Removes a single occurrence of an element from the purse, if present.
+    pub fn remove<T: Any + Eq>(&mut self, elem: T) -> bool {
+        self.inner.remove(elem)
+    }
+    /// Removes a single element of type `T` that borrows as `value`, if present. See
+    /// [`Purse::remove_borrowed`].
+    pub fn remove_borrowed<T, Q>(&mut self, value: &Q) -> bool
+    where
+        T: Any + Eq + Hash + Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.inner.remove_borrowed::<T, Q>(value)
+    }
+    /// Removes up to `n` occurrences of an element, returning how many were removed.
+    pub fn remove_n<T: Any + Eq>(&mut self, elem: &T, n: u64) -> u64 {
+        self.inner.remove_n(elem, n)
+    }
+    /// Removes every occurrence of an element, returning how many were removed.
+    pub fn remove_all<T: Any + Eq>(&mut self, elem: &T) -> u64 {
+        self.inner.remove_all(elem)
+    }
+    /// Removes and returns an element of type `T`. See [`Purse::pop`].
+    pub fn pop<T: Any>(&mut self) -> Option<T> {
+        self.inner.pop()
+    }
+    /// Removes and returns all elements of type `T`.
+    pub fn take_all_of_type<T: Any>(&mut self) -> Vec<T> {
+        self.inner.take_all_of_type()
+    }
+    /// Removes all elements of type `T`, returning them as an iterator.
+    pub fn drain_type<T: Any>(&mut self) -> alloc::vec::IntoIter<T> {
+        self.inner.drain_type()
+    }
+    /// Returns mutable references to all elements of type `T`. See
+    /// [`Purse::get_all_of_type_mut`].
+    pub fn get_all_of_type_mut<T: Any>(&mut self) -> Vec<&mut T> {
+        self.inner.get_all_of_type_mut()
+    }
+    /// Returns a lazy iterator over mutable references to all elements of type `T`.
+    pub fn iter_mut_of_type<T: Any>(&mut self) -> TypeIterMut<'_, T> {
+        self.inner.iter_mut_of_type()
+    }
+    /// Returns the elements of type `T` as a mutable slice, if they are stored in a list.
+    pub fn as_mut_slice<T: Any>(&mut self) -> Option<&mut [T]> {
+        self.inner.as_mut_slice()
+    }
+    /// Makes room for at least `additional` more elements of type `T`. See
+    /// [`Purse::reserve`].
+    pub fn reserve<T: Any + Send + Sync>(&mut self, additional: usize) {
+        self.inner.reserve::<T>(additional);
+    }
+    /// Frees the memory the purse holds beyond its elements. See
+    /// [`Purse::shrink_to_fit`].
+    pub fn shrink_to_fit(&mut self) {
+        self.inner.shrink_to_fit();
+    }
+    /// Removes all elements, keeping registrations.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+    /// Unwraps the underlying purse.
+    pub fn into_inner(self) -> Purse {
+        self.inner
+    }
+}
+
+impl Deref for SendPurse {
+    type Target = Purse;
+
+    fn deref(&self) -> &Purse {
+        &self.inner
+    }
+}
+
+impl From<SendPurse> for Purse {
+    fn from(purse: SendPurse) -> Self {
+        purse.inner
+    }
+}
+
+/// A purse that can be changed through a shared reference from many threads.
+///
+/// Each type lives in its own shard behind a read-write lock, so threads working
+/// on different types never wait on each other, and threads only reading a type
+/// share its lock. Only inserting the first element of a new type briefly locks
+/// the whole purse.
+///
+/// A panic while a shard is locked doesn't poison the purse: the shard holds
+/// whatever the interrupted operation left behind, and remains usable.
+///
+/// # Examples
+///
+/// ```
+/// # use purse::ConcurrentPurse;
+/// use std::thread;
+///
+/// let purse = ConcurrentPurse::new();
+/// thread::scope(|s| {
+///     for i in 0..4u32 {
+///         let purse = &purse;
+///         s.spawn(move || {
+///             purse.insert(i);
+///             purse.insert(format!("worker {i}"));
+///         });
+///     }
+/// });
+/// assert_eq!(purse.count::<u32>(), 4);
+/// assert!(purse.contains(String::from("worker 2")));
+/// assert!(purse.remove(3u32));
+/// assert_eq!(purse.len(), 7);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct ConcurrentPurse {
+    shards: RwLock<HashMap<TypeId, RwLock<SendPurse>>>,
+}
+
+#[cfg(feature = "std")]
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+fn lock<T>(lock: &Mutex<T>) -> MutexGuard<'_, T> {
+    lock.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+impl ConcurrentPurse {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Runs `f` on the shard of type `T`, if there is one.
+    fn read_shard<T: Any, R>(&self, f: impl FnOnce(&SendPurse) -> R) -> Option<R> {
+        let shards = read(&self.shards);
+        let shard = shards.get(&TypeId::of::<T>())?;
+        let result = f(&read(shard));
+        Some(result)
+    }
+    /// Runs `f` on the shard of type `T`, if there is one.
+    fn write_shard<T: Any, R>(&self, f: impl FnOnce(&mut SendPurse) -> R) -> Option<R> {
+        let shards = read(&self.shards);
+        let shard = shards.get(&TypeId::of::<T>())?;
+        let result = f(&mut write(shard));
+        Some(result)
+    }
+    /// Runs `f` on the shard of type `T`, creating the shard if it doesn't exist.
+    fn write_shard_or_default<T: Any, R>(&self, f: impl FnOnce(&mut SendPurse) -> R) -> R {
+        {
+            let shards = read(&self.shards);
+            if let Some(shard) = shards.get(&TypeId::of::<T>()) {
+                return f(&mut write(shard));
+            }
+        }
+        let mut shards = write(&self.shards);
+        let shard = shards.entry(TypeId::of::<T>()).or_default();
+        f(shard.get_mut().unwrap_or_else(PoisonError::into_inner))
+    }
+    /// Chooses how the elements of type `T` are stored. See [`Purse::register`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `strategy` was made with [`Strategy::custom`], see
+    /// [`SendPurse::register`].
+    pub fn register<T: Any + Send + Sync>(&self, strategy: Strategy<T>) {
+        self.write_shard_or_default::<T, _>(|shard| shard.register(strategy));
+    }
+    /// Switches the storage for type `T` to a hash-indexed bag. See
+    /// [`Purse::register_hashed`].
+    pub fn register_hashed<T: Any + Send + Sync + Hash + Eq + Clone>(&self) {
+        self.register(Strategy::<T>::hashed());
+    }
+    /// Switches the storage for type `T` to an ordered bag. See
+    /// [`Purse::register_ordered`].
+    pub fn register_ordered<T: Any + Send + Sync + Ord + Clone>(&self) {
+        self.register(Strategy::<T>::ordered());
+    }
+    /// Inserts an element into the purse.
+    pub fn insert<T: Any + Send + Sync>(&self, elem: T) {
+        self.write_shard_or_default::<T, _>(|shard| shard.insert(elem));
+    }
+    /// Inserts `n` copies of an element into the purse. See [`Purse::insert_n`].
+    pub fn insert_n<T: Any + Send + Sync + Clone>(&self, elem: T, n: u64) {
+        if n == 0 {
+            return;
+        }
+        self.write_shard_or_default::<T, _>(|shard| shard.insert_n(elem, n));
+    }
+    /// Checks if the purse contains a specific element.
+    pub fn contains<T: Any + Eq>(&self, t: T) -> bool {
+        self.read_shard::<T, _>(|shard| shard.contains(t))
+            .unwrap_or(false)
+    }
+    /// Counts the elements of type `T`.
+    pub fn count<T: Any>(&self) -> u64 {
+        self.read_shard::<T, _>(|shard| shard.count::<T>())
+            .unwrap_or(0)
+    }
+    /// Counts the occurrences of a specific element.
+    pub fn count_of<T: Any + Eq>(&self, elem: &T) -> u64 {
+        self.read_shard::<T, _>(|shard| shard.count_of(elem))
+            .unwrap_or(0)
+    }
+    /// Removes a single occurrence of an element from the purse, if present.
+    pub fn remove<T: Any + Eq>(&self, elem: T) -> bool {
+        self.write_shard::<T, _>(|shard| shard.remove(elem))
+            .unwrap_or(false)
+    }
+    /// Removes up to `n` occurrences of an element, returning how many were removed.
+    pub fn remove_n<T: Any + Eq>(&self, elem: &T, n: u64) -> u64 {
+        self.write_shard::<T, _>(|shard| shard.remove_n(elem, n))
+            .unwrap_or(0)
+    }
+    /// Removes and returns an element of type `T`. See [`Purse::pop`].
+    pub fn pop<T: Any>(&self) -> Option<T> {
+        self.write_shard::<T, _>(|shard| shard.pop()).flatten()
+    }
+    /// Removes and returns all elements of type `T`.
+    pub fn take_all_of_type<T: Any>(&self) -> Vec<T> {
+        self.write_shard::<T, _>(|shard| shard.take_all_of_type())
+            .unwrap_or_default()
+    }
+    /// Calls `f` with an iterator over the elements of type `T`, holding the type's
+    /// shard locked for reading meanwhile.
+    ///
+    /// `f` must not insert elements of a type the purse hasn't seen yet, as that
+    /// waits for every shard to be unlocked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::ConcurrentPurse;
+    /// let purse = ConcurrentPurse::new();
+    /// purse.insert_n(2u8, 3);
+    /// let sum: u32 = purse.with_type::<u8, _>(|items| items.map(|&n| u32::from(n)).sum());
+    /// assert_eq!(sum, 6);
+    /// ```
+    pub fn with_type<T: Any, R>(&self, f: impl FnOnce(TypeIter<'_, T>) -> R) -> R {
+        let shards = read(&self.shards);
+        match shards.get(&TypeId::of::<T>()) {
+            Some(shard) => f(read(shard).iter_of_type()),
+            None => f(TypeIter::from_slice(&[])),
+        }
+    }
+    /// Returns the total number of elements.
+    ///
+    /// Shards are counted one after another, so elements inserted or removed
+    /// meanwhile may or may not be included.
+    pub fn len(&self) -> usize {
+        read(&self.shards)
+            .values()
+            .map(|shard| read(shard).len())
+            .sum()
+    }
+    /// Checks if the purse is empty. See [`len`](ConcurrentPurse::len).
+    pub fn is_empty(&self) -> bool {
+        read(&self.shards)
+            .values()
+            .all(|shard| read(shard).is_empty())
+    }
+    /// Returns the number of distinct types with at least one element.
+    pub fn len_types(&self) -> usize {
+        read(&self.shards)
+            .values()
+            .filter(|shard| !read(shard).is_empty())
+            .count()
+    }
+    /// Removes all elements, keeping registrations.
+    pub fn clear(&self) {
+        read(&self.shards)
+            .values()
+            .for_each(|shard| write(shard).clear());
+    }
+    /// Merges the shards into a single purse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::ConcurrentPurse;
+    /// let purse = ConcurrentPurse::new();
+    /// purse.register_ordered::<u8>();
+    /// purse.insert(2u8);
+    /// purse.insert(1u8);
+    /// purse.insert("one");
+    ///
+    /// let purse = purse.into_purse();
+    /// assert_eq!(purse.get_all_of_type::<u8>(), vec![&1, &2]);
+    /// assert_eq!(purse.len_types(), 2);
+    /// ```
+    pub fn into_purse(self) -> SendPurse {
+        let shards = self
+            .shards
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut merged = Purse::new();
+        for shard in shards.into_values() {
+            let shard = shard.into_inner().unwrap_or_else(PoisonError::into_inner);
+            let Purse {
+                data,
+                factories,
+                vtables,
+                ..
+            } = shard.inner;
+            merged.data.extend(data);
+            merged.factories.extend(factories);
+            merged.vtables.extend(vtables);
+        }
+        SendPurse { inner: merged }
+    }
+}
+
+/// A purse of messages that threads can wait on.
+///
+/// Producers [`insert`](Mailbox::insert) messages of any `Send` type, and
+/// consumers take them out by type, either right away with
+/// [`try_take`](Mailbox::try_take) or by parking the thread until one arrives
+/// with [`take_blocking`](Mailbox::take_blocking) and
+/// [`take_timeout`](Mailbox::take_timeout). Async code can await one with
+/// [`wait_for`](Mailbox::wait_for) instead. Messages of a type are taken in the
+/// order they were inserted.
+///
+/// # Examples
+///
+/// ```
+/// # use purse::Mailbox;
+/// use std::thread;
+///
+/// struct Job(u32);
+///
+/// let mailbox = Mailbox::new();
+/// thread::scope(|s| {
+///     let worker = s.spawn(|| {
+///         let first = mailbox.take_blocking::<Job>();
+///         let second = mailbox.take_blocking::<Job>();
+///         first.0 + second.0
+///     });
+///     mailbox.insert("not a job");
+///     mailbox.insert(Job(1));
+///     mailbox.insert(Job(2));
+///     assert_eq!(worker.join().unwrap(), 3);
+/// });
+/// assert_eq!(mailbox.len(), 1);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct Mailbox {
+    inbox: Mutex<Inbox>,
+}
+
+#[cfg(feature = "std")]
+/// The state of a [`Mailbox`] behind its lock.
+#[derive(Debug, Default)]
+struct Inbox {
+    /// The messages, each type in a [`QueueColumn`].
+    purse: Purse,
+    /// Threads waiting for a message of each type park on the type's condition
+    /// variable, which only insertions of the type notify.
+    arrived: HashMap<TypeId, Arc<Condvar>>,
+    /// Tasks waiting for a message of each type, woken by the next insertion of
+    /// the type, under the slot of the [`WaitFor`] that registered them.
+    wakers: HashMap<TypeId, Vec<(u64, Waker)>>,
+    /// The slot the next [`WaitFor`] to register a waker takes.
+    next_slot: u64,
+}
+
+// SAFETY: Only `Send` messages go into the purse, and only into queue columns,
+// which are `Send` for them. The purse is only reached behind the mailbox's
+// lock and never lends out its messages, so they are not shared between
+// threads.
+#[cfg(feature = "std")]
+unsafe impl Send for Inbox {}
+
+#[cfg(feature = "std")]
+impl Inbox {
+    fn insert<T: Any + Send>(&mut self, message: T) {
+        if self.purse.column::<T>().is_none() {
+            self.purse.register::<T>(Strategy::new(|| {
+                Bucket::new(Box::<QueueColumn<T>>::default())
+            }));
+        }
+        self.purse.insert(message);
+    }
+    fn take_oldest<T: Any>(&mut self) -> Option<T> {
+        self.purse.column_mut::<T>()?.pop()
+    }
+    /// Returns the condition variable the threads waiting for a `T` park on.
+    fn arrived<T: Any>(&mut self) -> Arc<Condvar> {
+        Arc::clone(self.arrived.entry(TypeId::of::<T>()).or_default())
+    }
+    /// Registers `waker` under `slot` to be woken by the next message of type
+    /// `type_id`, replacing the waker registered under it before.
+    fn register_waker(&mut self, type_id: TypeId, slot: u64, waker: &Waker) {
+        let wakers = self.wakers.entry(type_id).or_default();
+        match wakers.iter_mut().find(|(s, _)| *s == slot) {
+            Some((_, registered)) if registered.will_wake(waker) => {}
+            Some((_, registered)) => registered.clone_from(waker),
+            None => wakers.push((slot, waker.clone())),
+        }
+    }
+    /// Drops the waker registered under `slot`, if a message hasn't woken it yet.
+    fn deregister_waker(&mut self, type_id: TypeId, slot: u64) {
+        if let Some(wakers) = self.wakers.get_mut(&type_id) {
+            wakers.retain(|(s, _)| *s != slot);
+            if wakers.is_empty() {
+                self.wakers.remove(&type_id);
+            }
+        }
+    }
+}
+
+/// Keeps the messages of a type in a `VecDeque<T>`, in order of insertion.
+#[cfg(feature = "std")]
+struct QueueColumn<T>(VecDeque<T>);
+
+#[cfg(feature = "std")]
+impl<T> Default for QueueColumn<T> {
+    fn default() -> Self {
+        Self(VecDeque::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Any> Column<T> for QueueColumn<T> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn insert(&mut self, value: T) {
+        self.0.push_back(value);
+    }
+    fn iter(&self) -> TypeIter<'_, T> {
+        TypeIter::new(&self.0)
+    }
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&T) -> bool, limit: u64) -> u64 {
+        let mut removed = 0;
+        self.0.retain(|elem| {
+            if removed < limit && matches(elem) {
+                removed += 1;
+                return false;
+            }
+            true
+        });
+        removed
+    }
+    /// Removes the oldest item.
+    fn pop(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+    fn take_all(&mut self) -> Vec<T> {
+        self.0.drain(..).collect()
+    }
+    fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+    fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+}
+
+#[cfg(feature = "std")]
+impl Mailbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Inserts a message, waking up the threads and tasks waiting for a message of
+    /// its type.
+    pub fn insert<T: Any + Send>(&self, message: T) {
+        let (arrived, wakers) = {
+            let mut inbox = lock(&self.inbox);
+            inbox.insert(message);
+            let type_id = TypeId::of::<T>();
+            (
+                inbox.arrived.get(&type_id).cloned(),
+                inbox.wakers.remove(&type_id),
+            )
+        };
+        if let Some(arrived) = arrived {
+            arrived.notify_all();
+        }
+        for (_, waker) in wakers.into_iter().flatten() {
+            waker.wake();
+        }
+    }
+    /// Takes the oldest message of type `T`, if there is one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Mailbox;
+    /// let mailbox = Mailbox::new();
+    /// assert_eq!(mailbox.try_take::<u8>(), None);
+    /// mailbox.insert(1u8);
+    /// mailbox.insert(2u8);
+    /// assert_eq!(mailbox.try_take::<u8>(), Some(1));
+    /// ```
+    pub fn try_take<T: Any>(&self) -> Option<T> {
+        lock(&self.inbox).take_oldest()
+    }
+    /// Takes the oldest message of type `T`, parking the calling thread until there
+    /// is one.
+    pub fn take_blocking<T: Any>(&self) -> T {
+        let mut inbox = lock(&self.inbox);
+        let arrived = inbox.arrived::<T>();
+        let mut inbox = arrived
+            .wait_while(inbox, |inbox| inbox.purse.count::<T>() == 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        inbox
+            .take_oldest()
+            .expect("waiting stops once a message of the type arrived")
+    }
+    /// Takes the oldest message of type `T`, parking the calling thread until there
+    /// is one or `timeout` has passed.
+    ///
+    /// # Returns
+    /// Returns `None` if no message of type `T` arrived in time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Mailbox;
+    /// use std::time::Duration;
+    ///
+    /// let mailbox = Mailbox::new();
+    /// mailbox.insert("ping");
+    /// assert_eq!(mailbox.take_timeout::<&str>(Duration::ZERO), Some("ping"));
+    /// assert_eq!(mailbox.take_timeout::<&str>(Duration::from_millis(10)), None);
+    /// ```
+    pub fn take_timeout<T: Any>(&self, timeout: Duration) -> Option<T> {
+        let mut inbox = lock(&self.inbox);
+        let arrived = inbox.arrived::<T>();
+        let (mut inbox, _) = arrived
+            .wait_timeout_while(inbox, timeout, |inbox| inbox.purse.count::<T>() == 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        inbox.take_oldest()
+    }
+    /// Returns a future that resolves with the oldest message of type `T` once there
+    /// is one.
+    ///
+    /// The future doesn't depend on any particular executor: it registers the
+    /// waker of the task polling it, which the next [`insert`](Mailbox::insert) of
+    /// a `T` wakes. Dropping the future before it resolves gives up the wait
+    /// without taking anything, and drops the waker it registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use purse::Mailbox;
+    /// # use std::future::Future;
+    /// # use std::pin::pin;
+    /// # use std::sync::Arc;
+    /// # use std::task::{Context, Poll, Wake, Waker};
+    /// # use std::thread::{self, Thread};
+    /// # struct Unpark(Thread);
+    /// # impl Wake for Unpark {
+    /// #     fn wake(self: Arc<Self>) {
+    /// #         self.0.unpark();
+    /// #     }
+    /// # }
+    /// # fn block_on<F: Future>(future: F) -> F::Output {
+    /// #     let mut future = pin!(future);
+    /// #     let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    /// #     let mut cx = Context::from_waker(&waker);
+    /// #     loop {
+    /// #         match future.as_mut().poll(&mut cx) {
+    /// #             Poll::Ready(output) => return output,
+    /// #             Poll::Pending => thread::park(),
+    /// #         }
+    /// #     }
+    /// # }
+    /// let mailbox = Mailbox::new();
+    /// thread::scope(|s| {
+    ///     s.spawn(|| mailbox.insert(String::from("hello")));
+    ///     let message = block_on(mailbox.wait_for::<String>());
+    ///     assert_eq!(message, "hello");
+    /// });
+    /// ```
+    pub fn wait_for<T: Any>(&self) -> WaitFor<'_, T> {
+        WaitFor {
+            mailbox: self,
+            type_id: TypeId::of::<T>(),
+            slot: None,
+            _marker: PhantomData,
+        }
+    }
+    /// Counts the messages of type `T` waiting to be taken.
+    pub fn count<T: Any>(&self) -> u64 {
+        lock(&self.inbox).purse.count::<T>()
+    }
+    /// Returns the number of messages waiting to be taken.
+    pub fn len(&self) -> usize {
+        lock(&self.inbox).purse.len()
+    }
+    /// Checks if there are no messages waiting to be taken.
+    pub fn is_empty(&self) -> bool {
+        lock(&self.inbox).purse.is_empty()
+    }
+    /// Returns the messages that were never taken.
+    pub fn into_purse(self) -> Purse {
+        self.inbox
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .purse
+    }
+}
+
+/// A future resolving with a message of type `T` from a [`Mailbox`].
+///
+/// Created by [`Mailbox::wait_for`].
+#[cfg(feature = "std")]
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitFor<'a, T> {
+    mailbox: &'a Mailbox,
+    type_id: TypeId,
+    /// The slot the future's waker is registered under, once it has been polled.
+    slot: Option<u64>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Any> Future for WaitFor<'_, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = self.get_mut();
+        let mut inbox = lock(&this.mailbox.inbox);
+        if let Some(message) = inbox.take_oldest() {
+            if let Some(slot) = this.slot.take() {
+                inbox.deregister_waker(this.type_id, slot);
+            }
+            return Poll::Ready(message);
+        }
+        let slot = *this.slot.get_or_insert_with(|| {
+            inbox.next_slot += 1;
+            inbox.next_slot
+        });
+        inbox.register_waker(this.type_id, slot, cx.waker());
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Drop for WaitFor<'_, T> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            lock(&self.mailbox.inbox).deregister_waker(self.type_id, slot);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> fmt::Debug for WaitFor<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitFor")
+            .field("type", &std::any::type_name::<T>())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,128 @@
+//! Per-type function tables that let a [`Purse`](crate::Purse) clone, compare,
+//! hash and print its contents.
+//!
+//! A purse only knows its items as `dyn Any`, which supports none of these
+//! operations. Types inserted through
+//! [`Purse::insert_value`](crate::Purse::insert_value) get a [`VTable`] of
+//! monomorphized functions recorded under their `TypeId`, which the purse's
+//! trait impls then look up for each type it holds.
+
+use core::any::Any;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use crate::algebra::{self, SetOp};
+use crate::map::{self, HashMap};
+use crate::sequenced::insert_sequenced;
+use crate::Bucket;
+
+/// A value whose `Clone`, `Eq`, `Hash` and `Debug` implementations can be
+/// recorded by a [`Purse`](crate::Purse).
+///
+/// This trait is implemented for every type meeting its bounds and does not
+/// need to be implemented by hand.
+pub trait ErasedValue: Any + Clone + Eq + Hash + fmt::Debug {}
+
+impl<T: Any + Clone + Eq + Hash + fmt::Debug> ErasedValue for T {}
+
+/// Operations on a [`Bucket`] holding items of one concrete type.
+#[derive(Clone, Copy)]
+pub(crate) struct VTable {
+    /// Creates an empty list bucket for the type.
+    pub(crate) empty: fn() -> Bucket,
+    /// Creates an empty bucket for the type in an insertion-ordered purse.
+    pub(crate) sequenced: fn() -> Bucket,
+    /// Inserts clones of every item of the first bucket into the second.
+    pub(crate) clone_into: fn(&Bucket, &mut Bucket),
+    pub(crate) eq: fn(&Bucket, &Bucket) -> bool,
+    pub(crate) hash: fn(&Bucket, &mut dyn Hasher),
+    pub(crate) fmt: fn(&Bucket, &mut fmt::Formatter<'_>) -> fmt::Result,
+    pub(crate) combine: fn(&mut Bucket, &Bucket, SetOp),
+    /// Returns how many items `combine` would leave in the first bucket.
+    pub(crate) combined_len: fn(&Bucket, &Bucket, SetOp) -> u64,
+    pub(crate) is_subset: fn(&Bucket, &Bucket) -> bool,
+}
+
+impl VTable {
+    pub(crate) fn of<T: ErasedValue>() -> Self {
+        Self {
+            empty: Bucket::list::<T>,
+            sequenced: Bucket::sequenced::<T>,
+            clone_into: clone_into::<T>,
+            eq: eq::<T>,
+            hash: hash::<T>,
+            fmt: fmt::<T>,
+            combine: algebra::combine::<T>,
+            combined_len: algebra::combined_len::<T>,
+            is_subset: algebra::is_subset::<T>,
+        }
+    }
+}
+
+impl fmt::Debug for VTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VTable").finish_non_exhaustive()
+    }
+}
+
+/// Groups runs of equal adjacent items, which is how columns that count their
+/// values rather than store each one yield them.
+pub(crate) fn runs<'a, T: Eq + 'a>(
+    items: impl Iterator<Item = &'a T>,
+) -> impl Iterator<Item = (&'a T, u64)> {
+    let mut items = items.peekable();
+    core::iter::from_fn(move || {
+        let value = items.next()?;
+        let mut n = 1;
+        while items.next_if_eq(&value).is_some() {
+            n += 1;
+        }
+        Some((value, n))
+    })
+}
+
+fn clone_into<T: ErasedValue>(source: &Bucket, target: &mut Bucket) {
+    let Some(column) = target.column_mut::<T>() else {
+        return;
+    };
+    // Keeping the sequence numbers keeps the copy's items interleaved with the
+    // other types the same way.
+    if let Some(sequence) = source.sequence() {
+        let elems = sequence.iter().copied().zip(source.iter_of::<T>().cloned());
+        insert_sequenced(column, elems.collect());
+        return;
+    }
+    for (value, n) in runs(source.iter_of::<T>()) {
+        column.insert_n(value.clone(), n);
+    }
+}
+
+/// Counts how many times each distinct value occurs, so that two buckets
+/// can be compared regardless of how their items are ordered or stored.
+pub(crate) fn multiplicities<T: ErasedValue>(bucket: &Bucket) -> HashMap<&T, u64> {
+    let mut counts = HashMap::default();
+    for value in bucket.iter_of::<T>() {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn eq<T: ErasedValue>(a: &Bucket, b: &Bucket) -> bool {
+    a.len() == b.len() && multiplicities::<T>(a) == multiplicities::<T>(b)
+}
+
+fn hash<T: ErasedValue>(bucket: &Bucket, state: &mut dyn Hasher) {
+    // Items are summed rather than fed in sequence, so that the hash does not
+    // depend on their order and agrees with `eq`.
+    let sum = bucket.iter_of::<T>().fold(0u64, |sum, value| {
+        let mut hasher = map::fixed_hasher();
+        value.hash(&mut hasher);
+        sum.wrapping_add(hasher.finish())
+    });
+    state.write_usize(bucket.len());
+    state.write_u64(sum);
+}
+
+fn fmt<T: ErasedValue>(bucket: &Bucket, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list().entries(bucket.iter_of::<T>()).finish()
+}